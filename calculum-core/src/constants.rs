@@ -1,27 +1,19 @@
-use crate::constants::dimensions;
-use crate::constants::metric;
-use crate::constants::customary;
+pub mod dimensions;
+pub mod metric;
+pub mod customary;
 
+pub use dimensions::DimensionVector;
+pub use metric::{UnitPrefix, MetricBaseUnitAtom};
 
-impl UnitAtom {
-    pub fn to_dimension(&self) -> Dimension {
-        use Dimension;
-        use UnitAtom::*;
+/// A unit in terms of the metric base units:
+/// `(atom, factor, [(base atom, power), ...])`.
+pub type Definition = (&'static str, f64, &'static [(&'static str, i8)]);
 
-        match self {
-            Second | Hour | Day | Week => Dimension::Time,
-            Meter  => Dimension::Length,
-            Coulomb => Dimension::Charge,
-            Kelvin => Dimension::Temp,
-            Mole => Dimension::Amount,
-            Candela => Dimension::Luminosity,
-            _ => Dimension::Other(self.clone()) // .to_string() ?
-        }
-    }
-}
-
-impl From<String> for UnitAtom {
-    fn from(s: String) -> Self {
-        UnitAtom::Other(s)
-    }
+/// Looks up a derived or customary unit: its factor & its base units.
+/// (`calculum-lang` checks these against UCUM's essence.)
+pub fn definition(atom: &str) -> Option<(f64, &'static [(&'static str, i8)])> {
+    metric::DERIVED_UNITS.iter()
+        .chain(customary::CUSTOMARY_UNITS.iter())
+        .find(|entry| entry.0 == atom)
+        .map(|entry| (entry.1, entry.2))
 }
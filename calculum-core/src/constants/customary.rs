@@ -1,3 +1,21 @@
-use crate::constants::dimensions;
-use crate::constants::metric;
-use crate::constants::customary;
\ No newline at end of file
+use super::Definition;
+
+/// Customary (non-metric) units, reduced to the base units.
+pub const CUSTOMARY_UNITS: [Definition; 16] = [
+    ("min", 60., &[("s", 1)]),
+    ("h", 3600., &[("s", 1)]),
+    ("d", 86400., &[("s", 1)]),
+    ("wk", 604800., &[("s", 1)]),
+    ("deg", std::f64::consts::PI / 180., &[("rad", 1)]),
+    ("[in_i]", 0.0254, &[("m", 1)]),
+    ("[ft_i]", 0.3048, &[("m", 1)]),
+    ("[yd_i]", 0.9144, &[("m", 1)]),
+    ("[mi_i]", 1609.344, &[("m", 1)]),
+    ("[nmi_i]", 1852., &[("m", 1)]),
+    ("[gal_us]", 0.003785411784, &[("m", 3)]),
+    ("[lb_av]", 453.59237, &[("g", 1)]),
+    ("[oz_av]", 28.349523125, &[("g", 1)]),
+    ("[lbf_av]", 453.59237 * 9.80665, &[("g", 1), ("m", 1), ("s", -2)]),
+    ("[psi]", 453.59237 * 9.80665 / 0.00064516, &[("g", 1), ("m", -1), ("s", -2)]),
+    ("atm", 101325e3, &[("g", 1), ("m", -1), ("s", -2)]),
+];
@@ -1,59 +1,147 @@
-#[derive(Debug, PartialEq, PartialOrd)]
-pub enum Dimension<T> {
-    // Base
-    Time,
-    Length,
-    Mass,
-    Charge,
-    Temp,
-    Amount,
-    Luminosity,
-
-    // // "Dimensionless"
-    // PlaneAngle,
-    // SolidAngle,
-    //
-    // // "Derived"
-    //
-    // // From Length
-    // Area,
-    // Volume,
-    // LineicNum,
-    //
-    // // From Time
-    // Frequency,
-    //
-    // // Mechanics
-    // Velocity,
-    // Acceleration,
-    Force,
-    // Pressure,
-    // ViscosityDynamic,
-    // ViscosityKinematic,
-    // Energy,
-    // Action,
-    // Power,
-    //
-    // // Electricity & Magnetism
-    // Current,
-    // Potential,
-    // Capacitance,
-    // Resistance,
-    // Conductance,
-    // FluxMagnetic,
-    // Inductance,
-    // PermittivityElectric,
-    // PermittivityMagnetic,
-    //
-    // FluxLuminous,
-    // Illuminance,
-    // Brightness,
-    //
-    // // Radioactivity
-    // Radioactivity,
-    // IonDose,
-    // EnergyDose,
-    // DoseEquivalent,
-
-    Other(T)
-}
\ No newline at end of file
+use std::{fmt, ops};
+
+use crate::rational::Rational;
+
+/// Symbols of the base dimensions, in the order of `DimensionVector`'s
+/// exponents: length, time, mass, charge, temperature, amount of substance,
+/// luminous intensity, & plane angle (UCUM treats angles as a dimension).
+pub const BASE_DIMENSIONS: [&str; 8] = ["L", "T", "M", "Q", "Θ", "N", "J", "A"];
+
+/// A dimension as exponents over the base dimensions, e.g., force is
+/// `L.T-2.M`. Derived dimensions fall out of multiplying, dividing &
+/// raising these, so two units are commensurable iff their vectors are equal.
+///
+/// Exponents are rational, for the likes of noise densities (`V/Hz^(1/2)`
+/// is `L2.T-5/2.M.Q-1`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DimensionVector(pub [Rational; 8]);
+
+impl DimensionVector {
+    pub const DIMENSIONLESS: Self = DimensionVector::new([0, 0, 0, 0, 0, 0, 0, 0]);
+    pub const LENGTH: Self = DimensionVector::new([1, 0, 0, 0, 0, 0, 0, 0]);
+    pub const TIME: Self = DimensionVector::new([0, 1, 0, 0, 0, 0, 0, 0]);
+    pub const MASS: Self = DimensionVector::new([0, 0, 1, 0, 0, 0, 0, 0]);
+    pub const CHARGE: Self = DimensionVector::new([0, 0, 0, 1, 0, 0, 0, 0]);
+    pub const TEMPERATURE: Self = DimensionVector::new([0, 0, 0, 0, 1, 0, 0, 0]);
+    pub const AMOUNT: Self = DimensionVector::new([0, 0, 0, 0, 0, 1, 0, 0]);
+    pub const LUMINOSITY: Self = DimensionVector::new([0, 0, 0, 0, 0, 0, 1, 0]);
+    pub const ANGLE: Self = DimensionVector::new([0, 0, 0, 0, 0, 0, 0, 1]);
+
+    /// From integer exponents.
+    pub const fn new(exps: [i32; 8]) -> Self {
+        let mut rationals = [Rational::ZERO; 8];
+        let mut i = 0;
+        while i < 8 {
+            rationals[i] = Rational::from_integer(exps[i]);
+            i += 1;
+        }
+        DimensionVector(rationals)
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        *self == Self::DIMENSIONLESS
+    }
+
+    pub fn powi(&self, n: i8) -> Self {
+        self.pow(Rational::from(n))
+    }
+
+    pub fn pow(&self, exp: Rational) -> Self {
+        DimensionVector(self.0.map(|e| e * exp))
+    }
+
+    fn add_exponents(&self, other: &Self) -> Self {
+        let mut exps = self.0;
+        for (exp, other_exp) in exps.iter_mut().zip(other.0) {
+            *exp += other_exp;
+        }
+        DimensionVector(exps)
+    }
+}
+
+impl ops::Mul for DimensionVector {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        self.add_exponents(&other)
+    }
+}
+
+impl ops::Div for DimensionVector {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        self * other.powi(-1)
+    }
+}
+
+impl ops::MulAssign for DimensionVector {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl ops::DivAssign for DimensionVector {
+    fn div_assign(&mut self, other: Self) {
+        *self = *self / other;
+    }
+}
+
+impl fmt::Display for DimensionVector {
+    /// In UCUM's notation, e.g., `L.T-2.M` (and `1` if dimensionless).
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_dimensionless() {
+            return write!(f, "1")
+        }
+
+        let display: Vec<String> = BASE_DIMENSIONS.iter()
+            .zip(self.0)
+            .filter(|(_, exp)| !exp.is_zero())
+            .map(|(symbol, exp)| match exp.to_integer() {
+                Some(1) => symbol.to_string(),
+                _ => format!("{}{}", symbol, exp),
+            })
+            .collect();
+
+        write!(f, "{}", display.join("."))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::DimensionVector;
+    use crate::rational::Rational;
+
+    #[test]
+    fn it_multiplies_and_divides_dimensions() {
+        let velocity = DimensionVector::LENGTH / DimensionVector::TIME;
+        let force = DimensionVector::MASS * velocity / DimensionVector::TIME;
+
+        assert_eq!(force, DimensionVector::new([1, -2, 1, 0, 0, 0, 0, 0]));
+        assert_eq!(force / force, DimensionVector::DIMENSIONLESS);
+    }
+
+    #[test]
+    fn it_raises_dimensions_to_powers() {
+        assert_eq!(DimensionVector::LENGTH.powi(3), DimensionVector::new([3, 0, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(DimensionVector::TIME.powi(-1), DimensionVector::DIMENSIONLESS / DimensionVector::TIME);
+    }
+
+    #[test]
+    fn it_takes_rational_powers() {
+        let per_root_hz = DimensionVector::TIME.pow(Rational::new(1, 2));
+
+        assert_eq!(per_root_hz * per_root_hz, DimensionVector::TIME);
+        assert_eq!(per_root_hz.to_string(), "T1/2");
+        assert_eq!((DimensionVector::LENGTH / per_root_hz).to_string(), "L.T-1/2");
+    }
+
+    #[test]
+    fn it_displays_dimensions() {
+        let force = DimensionVector::new([1, -2, 1, 0, 0, 0, 0, 0]);
+
+        assert_eq!(force.to_string(), "L.T-2.M");
+        assert_eq!(DimensionVector::DIMENSIONLESS.to_string(), "1");
+    }
+}
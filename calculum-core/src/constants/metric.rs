@@ -1,3 +1,5 @@
+use super::{dimensions::DimensionVector, Definition};
+
 #[repr(i8)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum UnitPrefix {
@@ -24,7 +26,13 @@ pub enum UnitPrefix {
     Yocto = -24,
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone)]
+impl UnitPrefix {
+    pub fn factor(&self) -> f64 {
+        10f64.powi(self.clone() as i32)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub enum MetricBaseUnitAtom {
     Meter,
     Second,
@@ -36,6 +44,66 @@ pub enum MetricBaseUnitAtom {
     Mole,
 }
 
+impl MetricBaseUnitAtom {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        use MetricBaseUnitAtom::*;
+
+        match symbol {
+            "m" => Some(Meter),
+            "s" => Some(Second),
+            "g" => Some(Gram),
+            "rad" => Some(Radian),
+            "K" => Some(Kelvin),
+            "C" => Some(Coulomb),
+            "cd" => Some(Candela),
+            "mol" => Some(Mole),
+            _ => None,
+        }
+    }
+
+    /// Each base unit spans exactly one base dimension.
+    pub fn dimension(&self) -> DimensionVector {
+        use MetricBaseUnitAtom::*;
+
+        match self {
+            Meter => DimensionVector::LENGTH,
+            Second => DimensionVector::TIME,
+            Gram => DimensionVector::MASS,
+            Radian => DimensionVector::ANGLE,
+            Kelvin => DimensionVector::TEMPERATURE,
+            Coulomb => DimensionVector::CHARGE,
+            Candela => DimensionVector::LUMINOSITY,
+            Mole => DimensionVector::AMOUNT,
+        }
+    }
+}
+
+/// Derived metric units, reduced to the base units.
+pub const DERIVED_UNITS: [Definition; 22] = [
+    ("N", 1e3, &[("g", 1), ("m", 1), ("s", -2)]),
+    ("Pa", 1e3, &[("g", 1), ("m", -1), ("s", -2)]),
+    ("J", 1e3, &[("g", 1), ("m", 2), ("s", -2)]),
+    ("W", 1e3, &[("g", 1), ("m", 2), ("s", -3)]),
+    ("A", 1., &[("C", 1), ("s", -1)]),
+    ("V", 1e3, &[("g", 1), ("m", 2), ("s", -2), ("C", -1)]),
+    ("F", 1e-3, &[("C", 2), ("s", 2), ("g", -1), ("m", -2)]),
+    ("Ohm", 1e3, &[("g", 1), ("m", 2), ("s", -1), ("C", -2)]),
+    ("S", 1e-3, &[("C", 2), ("s", 1), ("g", -1), ("m", -2)]),
+    ("Wb", 1e3, &[("g", 1), ("m", 2), ("s", -1), ("C", -1)]),
+    ("T", 1e3, &[("g", 1), ("s", -1), ("C", -1)]),
+    ("H", 1e3, &[("g", 1), ("m", 2), ("C", -2)]),
+    ("Hz", 1., &[("s", -1)]),
+    ("sr", 1., &[("rad", 2)]),
+    ("lm", 1., &[("cd", 1), ("rad", 2)]),
+    ("lx", 1., &[("cd", 1), ("rad", 2), ("m", -2)]),
+    ("Bq", 1., &[("s", -1)]),
+    ("Gy", 1., &[("m", 2), ("s", -2)]),
+    ("l", 1e-3, &[("m", 3)]),
+    ("L", 1e-3, &[("m", 3)]),
+    ("t", 1e6, &[("g", 1)]),
+    ("bar", 1e8, &[("g", 1), ("m", -1), ("s", -2)]),
+];
+
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub enum DimensionlessUnitAtom {
     Pi,
@@ -1,5 +1,5 @@
-mod constants;
-/// It's importing the `units` module.
-mod unit;
-mod units;
-mod conversions;
\ No newline at end of file
+pub mod constants;
+pub mod unit;
+pub mod units;
+pub mod conversions;
+pub mod rational;
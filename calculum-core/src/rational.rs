@@ -0,0 +1,239 @@
+use std::{cmp, fmt, ops};
+
+/// A fraction in lowest terms, with a positive denominator, e.g., the
+/// exponent in `Hz^(1/2)`. Integers are fractions over `1`.
+///
+/// Arithmetic panics on overflow, like integer arithmetic in debug builds;
+/// use the `checked_` methods where the operands come from user input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numer: i32,
+    denom: i32,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+impl Rational {
+    pub const ZERO: Self = Rational { numer: 0, denom: 1 };
+    pub const ONE: Self = Rational { numer: 1, denom: 1 };
+
+    /// # Panics
+    /// If `denom` is zero.
+    pub fn new(numer: i32, denom: i32) -> Self {
+        Self::reduce(numer as i64, denom as i64).expect("A rational in range with a non-zero denominator")
+    }
+
+    fn reduce(numer: i64, denom: i64) -> Option<Self> {
+        if denom == 0 {
+            return None
+        }
+        let divisor = gcd(numer, denom) * denom.signum();
+
+        Some(Rational {
+            numer: i32::try_from(numer / divisor).ok()?,
+            denom: i32::try_from(denom / divisor).ok()?,
+        })
+    }
+
+    pub const fn from_integer(n: i32) -> Self {
+        Rational { numer: n, denom: 1 }
+    }
+
+    pub fn numer(&self) -> i32 {
+        self.numer
+    }
+
+    pub fn denom(&self) -> i32 {
+        self.denom
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numer == 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom == 1
+    }
+
+    pub fn to_integer(&self) -> Option<i32> {
+        self.is_integer().then_some(self.numer)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    /// `x` raised to this power (with `powi` for integers, so that, e.g.,
+    /// `(-2)^3` is `-8` rather than `NaN`).
+    pub fn power_of(&self, x: f64) -> f64 {
+        match self.to_integer() {
+            Some(n) => x.powi(n),
+            None => x.powf(self.to_f64()),
+        }
+    }
+
+    /// The fraction with the smallest denominator (up to `max_denom`) that's
+    /// within floating point error of `x`, e.g., `1/3` for `0.333...`.
+    pub fn approximate(x: f64, max_denom: i32) -> Option<Self> {
+        if !x.is_finite() {
+            return None
+        }
+
+        (1..=max_denom.max(1)).find_map(|denom| {
+            let numer = (x * denom as f64).round();
+            let close = (numer / denom as f64 - x).abs() <= 1e-9 * x.abs().max(1.);
+            (close && numer.abs() <= i32::MAX as f64).then(|| Rational::new(numer as i32, denom))
+        })
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        Self::reduce(
+            self.numer as i64 * other.denom as i64 + other.numer as i64 * self.denom as i64,
+            self.denom as i64 * other.denom as i64,
+        )
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        Self::reduce(self.numer as i64 * other.numer as i64, self.denom as i64 * other.denom as i64)
+    }
+
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        Self::reduce(self.numer as i64 * other.denom as i64, self.denom as i64 * other.numer as i64)
+    }
+}
+
+impl From<i32> for Rational {
+    fn from(n: i32) -> Self {
+        Rational::from_integer(n)
+    }
+}
+
+impl From<i8> for Rational {
+    fn from(n: i8) -> Self {
+        Rational::from(n as i32)
+    }
+}
+
+impl Default for Rational {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (self.numer as i64 * other.denom as i64).cmp(&(other.numer as i64 * self.denom as i64))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Neg for Rational {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Rational::from(-1) * self
+    }
+}
+
+impl ops::Add for Rational {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.checked_add(other).expect("Rational overflow")
+    }
+}
+
+impl ops::Sub for Rational {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl ops::Mul for Rational {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        self.checked_mul(other).expect("Rational overflow")
+    }
+}
+
+impl ops::Div for Rational {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        self.checked_div(other).expect("Rational overflow or division by zero")
+    }
+}
+
+impl ops::AddAssign for Rational {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl ops::MulAssign for Rational {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl fmt::Display for Rational {
+    /// `2`, `-1/2`, ...
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.denom {
+            1 => write!(f, "{}", self.numer),
+            _ => write!(f, "{}/{}", self.numer, self.denom),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Rational;
+
+    #[test]
+    fn it_reduces_to_lowest_terms() {
+        assert_eq!(Rational::new(2, 4), Rational::new(1, 2));
+        assert_eq!(Rational::new(3, -6), Rational::new(-1, 2));
+        assert_eq!(Rational::new(0, 5), Rational::ZERO);
+        assert_eq!(Rational::new(6, 3).to_integer(), Some(2));
+    }
+
+    #[test]
+    fn it_does_arithmetic() {
+        let half = Rational::new(1, 2);
+
+        assert_eq!(half + Rational::new(1, 3), Rational::new(5, 6));
+        assert_eq!(half - Rational::ONE, Rational::new(-1, 2));
+        assert_eq!(half * Rational::from(4), Rational::from(2));
+        assert_eq!(half / Rational::new(1, 4), Rational::from(2));
+        assert!(Rational::new(1, 3) < half);
+        assert_eq!(Rational::from(i32::MAX).checked_add(Rational::ONE), None);
+        assert_eq!(Rational::from(3).power_of(-2.), -8.);
+        assert_eq!(Rational::new(1, 2).power_of(9.), 3.);
+    }
+
+    #[test]
+    fn it_approximates_floats() {
+        assert_eq!(Rational::approximate(0.5, 100), Some(Rational::new(1, 2)));
+        assert_eq!(Rational::approximate(1. / 3., 100), Some(Rational::new(1, 3)));
+        assert_eq!(Rational::approximate(-2., 100), Some(Rational::from(-2)));
+        assert_eq!(Rational::approximate(std::f64::consts::PI, 100), None);
+    }
+
+    #[test]
+    fn it_displays_fractions() {
+        assert_eq!(Rational::new(-1, 2).to_string(), "-1/2");
+        assert_eq!(Rational::from(3).to_string(), "3");
+    }
+}
@@ -1,55 +1,24 @@
-use crate::constants::{UnitPrefix, Dimension, UnitAtom};
+use crate::constants::{DimensionVector, UnitPrefix};
+use crate::conversions::{reduce_atom, ReducedForm, ReducibleUnit};
+use crate::rational::Rational;
+pub use crate::conversions::UnitEq;
 
-#[derive(Debug, PartialEq)]
-pub struct MetricBaseUnit {  // g, m, s, ...
-    pub unit: MetricBaseUnitAtom,
-    pub power: i8,
-}
-
-#[derive(Debug, PartialEq)]  // kg, mm, h, ...
-pub struct MetricDerivedUnit {
-    pub prefix: UnitPrefix,
-    pub unit: MetricDerivedUnitAtom,
-    pub power: i8,
-}
-
-#[derive(Debug, PartialEq)]
-pub struct CustomaryUnit { // lbs, ft, gal, ...
-    pub unit: CustomaryUnitAtom,
-    pub power: i8,
-}
-
-#[derive(Debug, PartialEq)]
-pub struct Arbitrary { // [my_custom_units]
-    pub unit: String,
-    pub power: i8,
-}
-
-#[derive(Debug, PartialEq)]
-pub enum Unit {
-    Derived(MetricDerivedUnit),
-    Base(MetricBaseUnit),
-    Customary(CustomaryUnit),
-    Arbitrary(ArbitraryUnit)
+#[derive(Debug, PartialEq, Clone)]
+pub struct Unit {  // kg, m, [lbf_av], [my_custom_unit], ...
+    pub prefix: Option<UnitPrefix>,
+    pub atom: String,
+    pub power: Rational,
 }
 
 impl Unit {
-    pub fn is_metric(&self) -> bool {
-        match self {
-            Unit::Derived(_) | Unit::Base(_) => true,
-            _ => false
-        }
-    }
-
     pub fn is_base(&self) -> bool {
-        match self {
-            Unit::Base(_) => true,
-            _ => false
-        }
+        matches!(reduce_atom(&self.atom), Some(ReducedForm { amount, ref units })
+            if amount == 1. && units.len() == 1 && units[0].1 == Rational::ONE)
     }
 
-    pub fn dimension(&self) -> Dimension {
-        self.unit.to_dimension()
+    /// Unknown (i.e., arbitrary) units have no dimension.
+    pub fn dimension(&self) -> Option<DimensionVector> {
+        ReducibleUnit::dimension(self)
     }
 }
 
@@ -59,33 +28,77 @@ impl Unit {
         Unit::new(Some(UnitPrefix::None), atom, 1)
     }
 
-    pub fn new_bare(unit: &str, power: i8) -> Self {
+    pub fn new_bare(unit: &str, power: impl Into<Rational>) -> Self {
         Unit::new(Some(UnitPrefix::None), unit, power)
     }
 
-    pub fn new(prefix: Option<UnitPrefix>, unit: &str, power: i8) -> Self {
+    pub fn new(prefix: Option<UnitPrefix>, unit: &str, power: impl Into<Rational>) -> Self {
         Unit {
             prefix,
-            unit: UnitAtom::from(unit.to_string()),
-            power
+            atom: unit.to_string(),
+            power: power.into(),
         }
     }
 }
 
+impl ReducibleUnit for Unit {
+    fn to_base_units(&self) -> Option<ReducedForm> {
+        let reduced = reduce_atom(&self.atom)?;
+        let prefix = self.prefix.as_ref().map_or(1., UnitPrefix::factor);
+
+        Some(ReducedForm {
+            amount: self.power.power_of(prefix * reduced.amount),
+            units: reduced.units.into_iter()
+                .map(|(base, power)| (base, power * self.power))
+                .collect(),
+        })
+    }
+}
+
 impl UnitEq<&Self> for Unit {
     fn is_similar(&self, other: &Self) -> bool {
-        self.unit == other.unit && self.power == other.power
+        self.atom == other.atom && self.power == other.power
     }
 
     fn is_commensurable(&self, other: &Self) -> bool {
-        self.dimension() == other.dimension()
+        match (self.dimension(), other.dimension()) {
+            (Some(dim), Some(other_dim)) => dim == other_dim,
+            _ => self.is_similar(other),
+        }
     }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use crate::constants::{DimensionVector, UnitPrefix};
+    use crate::conversions::ReducibleUnit;
+    use crate::rational::Rational;
     use super::Unit;
-    use super::units::Units;
     use super::UnitEq;
-}
\ No newline at end of file
+
+    #[test]
+    fn it_reports_dimensions() {
+        let n = Unit::new_atomic("N");
+        let km2 = Unit::new(Some(UnitPrefix::Kilo), "m", 2);
+
+        assert_eq!(n.dimension(), Some(DimensionVector::new([1, -2, 1, 0, 0, 0, 0, 0])));
+        assert_eq!(km2.dimension(), Some(DimensionVector::LENGTH.powi(2)));
+        assert_eq!(Unit::new_atomic("[my_custom_unit]").dimension(), None);
+    }
+
+    #[test]
+    fn it_reduces_rational_powers() {
+        let per_root_khz = Unit::new(Some(UnitPrefix::Kilo), "Hz", Rational::new(-1, 2));
+        let reduced = per_root_khz.to_base_units().unwrap();
+
+        assert!((reduced.amount - 1e3f64.sqrt().recip()).abs() < 1e-12);
+        assert_eq!(per_root_khz.dimension(), Some(DimensionVector::TIME.pow(Rational::new(1, 2))));
+    }
+
+    #[test]
+    fn it_compares_dimensions_of_single_units() {
+        assert!(Unit::new_atomic("N").is_commensurable(&Unit::new_atomic("[lbf_av]")));
+        assert!(!Unit::new_atomic("N").is_commensurable(&Unit::new_atomic("J")));
+    }
+}
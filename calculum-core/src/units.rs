@@ -1,62 +1,98 @@
+use crate::conversions::{ReducedForm, ReducibleUnit};
+use crate::rational::Rational;
 use crate::unit::{UnitEq, Unit};
 
-mod units {
-    use super::UnitEq;
-    use super::Unit;
+#[derive(Debug)]
+pub enum Units {
+    Dim(Vec<Unit>),
+    DimLess
+}
 
-    #[derive(Debug, PartialEq)]
-    pub enum Units {
-        Dim(Vec<Unit>),
-        DimLess
+/// Constructors
+impl Units {
+    pub fn new(units: Vec<Unit>) -> Self {
+        match units.len() {
+            0 => Units::DimLess,
+            _ => Units::Dim(sort_units(units))
+        }
     }
+}
 
-    /// Constructors
-    impl Units {
-        pub fn new(units: Vec<Unit>) -> Self {
-            match units.len() {
-                0 => Units::DimLess,
-                _ => Units::Dim(sort_units(units))
-            }
+pub fn sort_units(mut units: Vec<Unit>) -> Vec<Unit> {
+    units.sort_by_key(|unit| unit.dimension());
+    units
+}
+
+impl Units {
+    fn units(&self) -> &[Unit] {
+        match self {
+            Units::Dim(units) => units,
+            Units::DimLess => &[],
         }
     }
 
-    pub fn sort_units(mut units: Vec<Unit>) -> Vec<Unit> {
-        units.sort_by(|a, b| a.dimension().partial_cmp(&b.dimension()).unwrap());
-        units
+    /// The atoms & powers, ignoring prefixes (`cm.s-1` is `m.s-1`).
+    fn atoms(&self) -> Vec<(&str, Rational)> {
+        let mut atoms: Vec<(&str, Rational)> = self.units().iter()
+            .map(|unit| (unit.atom.as_str(), unit.power))
+            .collect();
+        atoms.sort();
+        atoms
     }
+}
 
-    impl UnitEq<&Units> for Units {
-        fn is_similar(&self, other: &Self) -> bool {
-            use Units::*;
+impl ReducibleUnit for Units {
+    fn to_base_units(&self) -> Option<ReducedForm> {
+        let mut reduced = ReducedForm { amount: 1., units: vec![] };
 
-            match (self, other) {
-                (Dim(_self_units), Dim(_other_units)) => false,  // FIXME
-                (Dim(_), _) => false,
-                (_, Dim(_)) => false,
-                (_, _) => true,
-            }
+        for unit in self.units() {
+            let ReducedForm { amount, units } = unit.to_base_units()?;
+            reduced.amount *= amount;
+            reduced.units.extend(units);
         }
 
-        fn is_commensurable(&self, other: &Self) -> bool {
-            use Units::*;
+        Some(reduced.normalize())
+    }
+}
 
-            match (self, other) {
-                (Dim(_self_units), Dim(_other_units)) => false,  // FIXME
-                (Dim(_), _) => false,
-                (_, Dim(_)) => false,
-                (_, _) => true,
+impl PartialEq for Units {
+    /// Units are equal if they reduce to the same thing (`N` & `kg.m.s-2`).
+    /// Units that don't reduce have to be written the same way.
+    fn eq(&self, other: &Units) -> bool {
+        match (self.to_base_units(), other.to_base_units()) {
+            (Some(reduced), Some(other_reduced)) => reduced == other_reduced,
+            _ => {
+                let mut units = self.units().to_vec();
+                let mut other_units = other.units().to_vec();
+                units.sort_by(|a, b| (&a.atom, a.power).cmp(&(&b.atom, b.power)));
+                other_units.sort_by(|a, b| (&a.atom, a.power).cmp(&(&b.atom, b.power)));
+                units == other_units
             }
         }
     }
 }
 
+impl UnitEq<&Units> for Units {
+    fn is_similar(&self, other: &Self) -> bool {
+        self.atoms() == other.atoms()
+    }
+
+    fn is_commensurable(&self, other: &Self) -> bool {
+        match (self.dimension(), other.dimension()) {
+            (Some(dim), Some(other_dim)) => dim == other_dim,
+            // Arbitrary units are only commensurable with themselves
+            _ => self.is_similar(other),
+        }
+    }
+}
+
 
 
 #[cfg(test)]
 mod tests {
     use crate::constants::UnitPrefix;
     use super::Unit;
-    use super::units::Units;
+    use super::Units;
     use super::UnitEq;
 
 
@@ -87,18 +123,20 @@ mod tests {
         let _value = 1;
 
         let m = ||  Unit::new_atomic("m");
-        let g = || Unit::new_atomic("g");
+        let kg = || Unit::new(Some(UnitPrefix::Kilo), "g", 1);
         let s2 = || Unit::new_bare("s", -2);
         let n = || Unit::new_bare("N", 1);
 
         let us1 = Units::new(vec![n()]);
-        let us2 = Units::new(vec![g(), m(), s2()]);
+        let us2 = Units::new(vec![kg(), m(), s2()]);
 
         assert_eq!(us1, us2);
 
-        let _us3 = Units::new(vec![n(), m()]);
-        let _us4 = Units::new(vec![g(), m(), s2(), m()]);
+        let us3 = Units::new(vec![n(), m()]);
+        let us4 = Units::new(vec![kg(), m(), s2(), m()]);
 
+        assert_eq!(us3, us4);
+        assert_ne!(us1, us3);
     }
 
     #[test]
@@ -113,9 +151,37 @@ mod tests {
         assert!(km().is_similar(&cm()));
     }
 
+    #[test]
+    fn it_ignores_prefixes_when_comparing_units_for_similarity() {
+        let cm_s = Units::new(vec![Unit::new(Some(UnitPrefix::Centi), "m", 1), Unit::new_bare("s", -1)]);
+        let km_h = Units::new(vec![Unit::new(Some(UnitPrefix::Kilo), "m", 1), Unit::new_bare("h", -1)]);
+        let m_s = Units::new(vec![Unit::new_bare("s", -1), Unit::new_atomic("m")]);
+
+        assert!(cm_s.is_similar(&m_s));
+        assert!(m_s.is_similar(&cm_s));
+        assert!(!m_s.is_similar(&km_h));
+        assert!(Units::new(vec![]).is_similar(&Units::DimLess));
+    }
+
     #[test]
     fn it_checks_dimensions_when_detecting_compatibility() {
-        assert_eq!(true, false);
+        let n = Units::new(vec![Unit::new_atomic("N")]);
+        let kg_m_s2 = Units::new(vec![
+            Unit::new(Some(UnitPrefix::Kilo), "g", 1), Unit::new_atomic("m"), Unit::new_bare("s", -2)
+        ]);
+        let lbf = Units::new(vec![Unit::new_atomic("[lbf_av]")]);
+        let j = Units::new(vec![Unit::new_atomic("J")]);
+        let arb = || Units::new(vec![Unit::new_atomic("[arb'U]")]);
+
+        assert!(n.is_commensurable(&kg_m_s2));
+        assert!(kg_m_s2.is_commensurable(&lbf));
+        assert!(lbf.is_commensurable(&n));
+        assert!(!n.is_commensurable(&j));
+        assert!(!n.is_similar(&kg_m_s2));
+
+        assert!(arb().is_commensurable(&arb()));
+        assert!(!arb().is_commensurable(&Units::DimLess));
+        assert!(!Units::DimLess.is_commensurable(&n));
     }
 
 }
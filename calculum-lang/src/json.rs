@@ -0,0 +1,121 @@
+//! # JSON
+//! Just enough JSON to write results out with (for `--format json`).
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// `NaN` & the infinities are written as `null`, since JSON has no
+    /// numbers for them.
+    Number(f64),
+    String(String),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(x: f64) -> Self {
+        Json::Number(x)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Json::Null, Into::into)
+    }
+}
+
+fn write_string(s: &str, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    /// On a single line, e.g., `{"magnitude":2,"term":"kg/s"}`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(x) if x.is_finite() => write!(f, "{}", x),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(s, f),
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(key, f)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn it_writes_objects() {
+        let json = Json::Object(vec![
+            ("magnitude", 16.09344.into()),
+            ("term", "km".into()),
+            ("dimension", None::<&str>.into()),
+            ("span", Json::Object(vec![("start", 0usize.into()), ("end", 3usize.into())])),
+        ]);
+
+        assert_eq!(json.to_string(), r#"{"magnitude":16.09344,"term":"km","dimension":null,"span":{"start":0,"end":3}}"#);
+    }
+
+    #[test]
+    fn it_escapes_strings() {
+        assert_eq!(Json::from("[in_i'Hg] \"x\"\\\n\u{1}").to_string(), r#""[in_i'Hg] \"x\"\\\n\u0001""#);
+    }
+
+    #[test]
+    fn it_writes_non_finite_numbers_as_null() {
+        assert_eq!(Json::from(f64::NAN).to_string(), "null");
+        assert_eq!(Json::from(f64::NEG_INFINITY).to_string(), "null");
+        assert_eq!(Json::from(-2.5e-7).to_string(), "-0.00000025");
+    }
+}
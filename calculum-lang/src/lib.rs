@@ -0,0 +1,7 @@
+//! # Calculum
+//! A calculator for units and measures, with unit terms in UCUM.
+//!
+//! See `ucum` for the vocabulary (units, unit terms, quantities, and
+//! expressions).
+
+pub mod ucum;
@@ -1,25 +1,327 @@
-use std::io::{self, Write};
+//! # Calculum
+//!
+//! ```text
+//! calculum-lang                       # a REPL (see `repl`), or the lines piped to stdin
+//! calculum-lang run trip.calc         # a script, one statement per line
+//! calculum-lang -e "10 [mi_i] -> km"  # a statement (or several)
+//! ```
+//!
+//! Scripts, statements & piped input print the value of each expression,
+//! and stop at the first error with a non-zero exit code (so they can be
+//! used in shell scripts & Makefiles). Scripts are checked for dimensional
+//! errors before any of them runs.
+//!
+//! With `--format json`, each value & error is a JSON object on a line of
+//! its own, for other tools to read. As in text, values go to stdout &
+//! errors to stderr. Every value has a `kind` (`quantity` or `boolean`)
+//! & its `value` as text, along with the fields for its kind:
+//!
+//! ```text
+//! {"source":"-e","line":1,"input":"10 [mi_i] -> km","kind":"quantity","value":"16.09344 'km'","magnitude":16.09344,"term":"km","base_magnitude":16093.44,"base_term":"m","dimension":"L"}
+//! {"source":"-e","line":2,"input":"1 m < 1 km","kind":"boolean","value":"true","boolean":true}
+//! {"source":"-e","line":3,"input":"1 m + 1 s","error":{"kind":"Incommensurable","message":"...","span":{"start":0,"end":9}}}
+//! ```
 
-use crate::ucum::expression;
+use std::io::{self, BufRead, IsTerminal};
+use std::process::ExitCode;
+use std::{env, fs};
 
-mod ucum;
+use calculum_lang::ucum::error::CalculumError;
+use calculum_lang::ucum::expression::{self, Environment, Value};
+use calculum_lang::ucum::term::ReducibleUnit;
 
+use crate::json::Json;
+use crate::repl::Repl;
 
-fn main() {
-    let mut input = String::new();
+mod json;
+mod repl;
 
-    println!("\n-- Calculum ---------------------------------------------------------------\n");
+const USAGE: &str = "\
+Usage:
+  calculum-lang                 Start a REPL (or run the lines piped to stdin)
+  calculum-lang run <file>      Run a script, one statement per line
+  calculum-lang -e <statement>  Run a statement (can be repeated)
+  calculum-lang -h, --help      Show this message
 
-    loop {
-        print!(">>> ");
-        io::stdout().flush().expect("There was an error printing to stdout.");
+Options:
+  --format <text|json>          Print results as text (the default) or as
+                                one JSON object per line";
 
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
+#[derive(Debug, PartialEq)]
+enum Command {
+    Repl,
+    Stdin,
+    Run(String),
+    Eval(Vec<String>),
+    Help,
+}
 
-        println!("{:?}", expression::interpret(input.trim()));
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
 
-        input.clear()
+/// The command & output format. Without a command, a REPL starts if stdin
+/// is `interactive` (& the output is text).
+fn parse_args(mut args: impl Iterator<Item = String>, interactive: bool) -> Result<(Command, Format), String> {
+    let mut statements = vec![];
+    let mut positional = vec![];
+    let mut format = Format::Text;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok((Command::Help, format)),
+            "-e" => statements.push(args.next().ok_or("'-e' takes a statement.")?),
+            "--format" => format = match args.next().as_deref() {
+                Some("text") => Format::Text,
+                Some("json") => Format::Json,
+                _ => return Err("'--format' takes 'text' or 'json'.".to_string()),
+            },
+            flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'.", flag)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let command = match (positional.as_slice(), statements.is_empty()) {
+        ([], false) => Command::Eval(statements),
+        ([run, path], true) if run == "run" => Command::Run(path.clone()),
+        ([], true) if interactive && format == Format::Text => Command::Repl,
+        ([], true) => Command::Stdin,
+        _ => return Err(format!("Unexpected arguments: '{}'.", positional.join(" "))),
+    };
+    Ok((command, format))
+}
+
+/// Where a statement came from: line `n` of `source`.
+struct Line<'a> {
+    source: &'a str,
+    n: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    fn json(&self, fields: impl IntoIterator<Item = (&'static str, Json)>) -> Json {
+        let mut object = vec![
+            ("source", self.source.into()),
+            ("line", self.n.into()),
+            ("input", self.text.into()),
+        ];
+        object.extend(fields);
+        Json::Object(object)
+    }
+}
+
+/// A value's kind & text, with (for a quantity) its magnitude & unit term
+/// (as written), the base units it reduces to & their dimension. Special
+/// units (e.g., `Cel`) aren't multiples of their base units, so they have
+/// no base magnitude.
+fn value_json(value: &Value) -> Vec<(&'static str, Json)> {
+    let Value::Quantity(qn) = value else {
+        return vec![
+            ("kind", "boolean".into()),
+            ("value", value.to_string().into()),
+            ("boolean", value.as_bool().into()),
+        ]
+    };
+    let base = qn.term().as_base_units();
+
+    vec![
+        ("kind", "quantity".into()),
+        ("value", value.to_string().into()),
+        ("magnitude", qn.mag().into()),
+        ("term", qn.term().to_string().into()),
+        ("base_magnitude", qn.term().special().is_none().then(|| qn.mag() * base.mag).into()),
+        ("base_term", base.to_string().into()),
+        ("dimension", qn.term().dimension().map(|dim| dim.to_string()).into()),
+    ]
+}
+
+fn error_json(e: &CalculumError) -> Json {
+    Json::Object(vec![
+        ("kind", e.kind().into()),
+        ("message", e.message().into()),
+        ("span", Json::Object(vec![("start", e.span().start.into()), ("end", e.span().end.into())])),
+    ])
+}
+
+fn print(value: &Value, line: &Line, format: Format) {
+    match format {
+        Format::Text => println!("{}", value),
+        Format::Json => println!("{}", line.json(value_json(value))),
+    }
+}
+
+/// Reports an error in a line on stderr, with the part of the line at its
+/// span underlined (or as JSON):
+///
+/// ```text
+/// trip.calc:2:5: [Dimension Error] Units 'L' and 'T' are not commensurable. (at 4..9)
+///   y = x + t
+///       ^^^^^
+/// ```
+fn report(e: &CalculumError, line: &Line, format: Format) {
+    if format == Format::Json {
+        eprintln!("{}", line.json([("error", error_json(e))]));
+        return
+    }
+
+    let span = e.span();
+    let start = span.start.min(line.text.len());
+    let end = span.end.clamp(start, line.text.len());
+    let column = line.text[..start].chars().count();
+
+    eprintln!("{}:{}:{}: {}", line.source, line.n, column + 1, e);
+    eprintln!("  {}", line.text);
+    eprintln!("  {}{}", " ".repeat(column), "^".repeat(line.text[start..end].chars().count().max(1)));
+}
+
+/// Runs statements one at a time, printing the value of each expression.
+/// Stops at (& reports) the first error.
+fn run_lines(lines: impl Iterator<Item = io::Result<String>>, source: &str, env: &mut Environment, format: Format) -> ExitCode {
+    for (i, line) in lines.enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("{}: {}", source, e);
+                return ExitCode::FAILURE
+            },
+        };
+        if line.trim().is_empty() {
+            continue
+        }
+
+        let line = Line { source, n: i + 1, text: &line };
+        match expression::execute(line.text, env) {
+            Ok(Some(value)) => print(&value, &line, format),
+            Ok(None) => {},
+            Err(e) => {
+                report(&e, &line, format);
+                return ExitCode::FAILURE
+            },
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Checks the whole script (reporting every error) before running it.
+fn run_script(path: &str, env: &mut Environment, format: Format) -> ExitCode {
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return ExitCode::FAILURE
+        },
+    };
+
+    let errors = expression::check(&script, env);
+    for e in &errors {
+        // Spans are into the whole script, rather than the line
+        let span = e.span().clone();
+        let start = script[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let n = script[..start].matches('\n').count() + 1;
+        let line = Line { source: path, n, text: script[start..].lines().next().unwrap_or("") };
+        report(&e.clone().at(span.start - start..span.end - start), &line, format);
+    }
+    if !errors.is_empty() {
+        return ExitCode::FAILURE
+    }
+
+    run_lines(script.lines().map(|line| Ok(line.to_string())), path, env, format)
+}
+
+fn main() -> ExitCode {
+    // Definitions & variables carry over from one statement to the next
+    let mut env = Environment::new();
+
+    match parse_args(env::args().skip(1), io::stdin().is_terminal()) {
+        Ok((Command::Repl, _)) => match Repl::new(&mut env).run() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            },
+        },
+        Ok((Command::Stdin, format)) => run_lines(io::stdin().lock().lines(), "<stdin>", &mut env, format),
+        Ok((Command::Run(path), format)) => run_script(&path, &mut env, format),
+        Ok((Command::Eval(statements), format)) => {
+            run_lines(statements.into_iter().map(Ok), "-e", &mut env, format)
+        },
+        Ok((Command::Help, _)) => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        },
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(Command, Format), String> {
+        parse_args(args.iter().map(|arg| arg.to_string()), true)
+    }
+
+    #[test]
+    fn it_parses_commands() {
+        assert_eq!(parse(&[]), Ok((Command::Repl, Format::Text)));
+        assert_eq!(parse_args(std::iter::empty(), false), Ok((Command::Stdin, Format::Text)));
+        assert_eq!(parse(&["run", "trip.calc"]), Ok((Command::Run("trip.calc".to_string()), Format::Text)));
+        assert_eq!(
+            parse(&["-e", "1 m", "-e", "2 s"]),
+            Ok((Command::Eval(vec!["1 m".to_string(), "2 s".to_string()]), Format::Text))
+        );
+        assert_eq!(parse(&["run", "a.calc", "--help"]), Ok((Command::Help, Format::Text)));
+    }
+
+    #[test]
+    fn it_parses_formats() {
+        assert_eq!(parse(&["--format", "json", "-e", "1 m"]).map(|(_, f)| f), Ok(Format::Json));
+        assert_eq!(parse(&["-e", "1 m", "--format", "text"]).map(|(_, f)| f), Ok(Format::Text));
+        // JSON is for other tools, so it reads stdin even from a terminal
+        assert_eq!(parse(&["--format", "json"]), Ok((Command::Stdin, Format::Json)));
+    }
+
+    fn json(s: &str) -> String {
+        let mut env = Environment::new();
+        let value = expression::execute(s, &mut env).unwrap().unwrap();
+        Json::Object(value_json(&value)).to_string()
+    }
+
+    #[test]
+    fn it_writes_quantities_as_json() {
+        assert_eq!(
+            json("10 [mi_i] -> km"),
+            r#"{"kind":"quantity","value":"16.09344 'km'","magnitude":16.09344,"term":"km","base_magnitude":16093.44,"base_term":"m","dimension":"L"}"#
+        );
+        assert!(json("37 Cel").contains(r#""base_magnitude":null"#));
+    }
+
+    #[test]
+    fn it_writes_booleans_as_json() {
+        assert_eq!(json("1 m < 1 km"), r#"{"kind":"boolean","value":"true","boolean":true}"#);
+    }
+
+    #[test]
+    fn it_writes_errors_as_json() {
+        let e = CalculumError::UnknownVariable { name: "x".to_string(), span: 4..5 };
+
+        assert_eq!(
+            error_json(&e).to_string(),
+            r#"{"kind":"UnknownVariable","message":"[Name Error] Unknown variable 'x'.","span":{"start":4,"end":5}}"#
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_arguments() {
+        assert_eq!(parse(&["-e"]), Err("'-e' takes a statement.".to_string()));
+        assert_eq!(parse(&["--format", "xml"]), Err("'--format' takes 'text' or 'json'.".to_string()));
+        assert_eq!(parse(&["-x"]), Err("Unknown option '-x'.".to_string()));
+        assert_eq!(parse(&["run"]), Err("Unexpected arguments: 'run'.".to_string()));
+        assert_eq!(parse(&["run", "a.calc", "-e", "1 m"]), Err("Unexpected arguments: 'run a.calc'.".to_string()));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,366 @@
+//! # REPL
+//! Reads statements with a line editor, and keeps their history in
+//! `~/.calculum_history` (or `$CALCULUM_HISTORY`). A statement goes on over
+//! the next lines while it has unclosed parentheses.
+//!
+//! Lines starting with `:` are commands (see `HELP`). Errors, in statements
+//! & commands alike, are reported, and the session carries on.
+
+use std::path::PathBuf;
+use std::env;
+
+use calculum_core::constants::dimensions::BASE_DIMENSIONS;
+use calculum_core::constants::DimensionVector;
+use calculum_core::rational::Rational;
+use calculum_lang::ucum::error::CalculumError;
+use calculum_lang::ucum::expression::parser::{Expr, ExprKind};
+use calculum_lang::ucum::expression::{self, Checker, Environment, Value};
+use calculum_lang::ucum::term::{ReducibleUnit, UnitTerm};
+use rustyline::config::Config;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+const HELP: &str = "\
+Statements:
+  10 [mi_i] -> km               Evaluate an expression (with a conversion)
+  accel = 10 m/s2               Assign a variable
+  speed(d: m, t: s): m/s = d/t  Define a function
+  define [pallet] = 40 [ft_i]3  Define a unit
+  dimension [currency]          Define a base unit (for a dimension of its own)
+
+Commands:
+  :help                         Show this message
+  :quit                         Quit (as does Ctrl-D)
+  :vars                         List the variables & functions
+  :clear                        Forget the variables, functions & definitions
+  :units <dimension>            List the units of a dimension, written as in
+                                `L.T-1`, by name (`length`, `velocity`), or
+                                as a unit term (`m/s`)
+  :explain <expr>               Show the type of every part of an expression,
+                                then its value";
+
+const PROMPT: &str = ">>> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// How many more `(` than `)` there are.
+fn open_parens(s: &str) -> i32 {
+    s.chars().fold(0, |depth, c| match c {
+        '(' => depth + 1,
+        ')' => depth - 1,
+        _ => depth,
+    })
+}
+
+/// `$CALCULUM_HISTORY`, or `.calculum_history` in the home directory.
+fn history_path() -> Option<PathBuf> {
+    env::var_os("CALCULUM_HISTORY")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(|home| PathBuf::from(home).join(".calculum_history")))
+}
+
+/// A dimension as `DimensionVector` displays it, e.g., `L.T-2.M` or
+/// `L2.T-5/2` (and `1` for dimensionless).
+fn parse_dimension(s: &str) -> Option<DimensionVector> {
+    if s == "1" {
+        return Some(DimensionVector::DIMENSIONLESS)
+    }
+
+    s.split('.').try_fold(DimensionVector::DIMENSIONLESS, |mut dim, factor| {
+        let (i, symbol) = BASE_DIMENSIONS.iter().enumerate().find(|(_, symbol)| factor.starts_with(*symbol))?;
+        let exp = match factor[symbol.len()..].split_once('/') {
+            None if factor.len() == symbol.len() => Rational::ONE,
+            None => Rational::from(factor[symbol.len()..].parse::<i32>().ok()?),
+            Some((numer, denom)) => Rational::new(numer.parse().ok()?, denom.parse().ok().filter(|&d: &i32| d > 0)?),
+        };
+
+        // A repeated dimension adds up (`L.L` is `L2`), as long as it fits
+        dim.0[i] = dim.0[i].checked_add(exp)?;
+        Some(dim)
+    })
+}
+
+/// The dimension in `:units <dimension>`: written out (`L.T-1`), the name
+/// of a unit's property (`length`, `velocity`), or a unit term (`m/s`), in
+/// that order. (So `L` is a length, rather than a liter.)
+fn dimension_of(s: &str, env: &Environment) -> Option<DimensionVector> {
+    let dimension = |code: &str| UnitTerm::new(code, &env.registry).ok()?.dimension();
+
+    parse_dimension(s)
+        .or_else(|| {
+            let base_units = env.registry.base_units().map(|u| (&u.code, &u.property));
+            let mut units = base_units.chain(env.registry.units().map(|u| (&u.code, &u.property)));
+            units.find(|(_, property)| property.eq_ignore_ascii_case(s)).and_then(|(code, _)| dimension(code))
+        })
+        .or_else(|| dimension(s))
+}
+
+/// The parts of an expression, in order.
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match &expr.kind {
+        ExprKind::Neg(operand) | ExprKind::Convert(operand, _) => vec![operand],
+        ExprKind::Binary(_, lhs, rhs) => vec![lhs, rhs],
+        ExprKind::Call(_, args) => args.iter().collect(),
+        _ => vec![],
+    }
+}
+
+enum Flow {
+    Continue,
+    Quit,
+}
+
+pub struct Repl<'a> {
+    env: &'a mut Environment,
+}
+
+impl<'a> Repl<'a> {
+    pub fn new(env: &'a mut Environment) -> Self {
+        Repl { env }
+    }
+
+    /// Reads & runs statements until `:quit` or the end of input (Ctrl-D).
+    /// Ctrl-C drops the statement being written.
+    pub fn run(&mut self) -> rustyline::Result<()> {
+        let config = Config::builder().max_history_size(1_000)?.auto_add_history(false).build();
+        let mut editor = DefaultEditor::with_config(config)?;
+        let history = history_path();
+        if let Some(path) = &history {
+            // There's no history the first time around
+            let _ = editor.load_history(path);
+        }
+
+        println!("\n-- Calculum ---------------------------------------------------------------\n");
+        println!("Type :help for help.\n");
+
+        // The history is saved however the session ends
+        let result = self.read_eval(&mut editor);
+        let saved = history.as_ref().map_or(Ok(()), |path| editor.save_history(path));
+        result.and(saved)
+    }
+
+    fn read_eval(&mut self, editor: &mut DefaultEditor) -> rustyline::Result<()> {
+        let mut input = String::new();
+        let mut continued = false;
+        loop {
+            let prompt = if input.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    if input.is_empty() {
+                        input.push_str(line.trim_end());
+                    } else {
+                        input.push(' ');
+                        input.push_str(line.trim());
+                        continued = true;
+                    }
+                    if open_parens(&input) > 0 {
+                        continue
+                    }
+
+                    let statement = std::mem::take(&mut input);
+                    let echo = std::mem::take(&mut continued);
+                    if statement.trim().is_empty() {
+                        continue
+                    }
+                    editor.add_history_entry(statement.as_str())?;
+                    if let Flow::Quit = self.handle(&statement, echo) {
+                        return Ok(())
+                    }
+                },
+                Err(ReadlineError::Interrupted) => {
+                    input.clear();
+                    continued = false;
+                },
+                Err(ReadlineError::Eof) => {
+                    println!();
+                    return Ok(())
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Runs a statement or a command. `echo` is for statements that went
+    /// over more than one line, which errors have to repeat to point into.
+    fn handle(&mut self, statement: &str, echo: bool) -> Flow {
+        let start = statement.len() - statement.trim_start().len();
+        let Some(command) = statement[start..].strip_prefix(':') else {
+            match expression::execute(statement, self.env) {
+                Ok(Some(value)) => println!("{}", value),
+                Ok(None) => {},
+                Err(e) => report(&e, statement, echo),
+            }
+            return Flow::Continue
+        };
+
+        let name_len = command.find(char::is_whitespace).unwrap_or(command.len());
+        let (name, arg) = (&command[..name_len], command[name_len..].trim());
+        // Where the argument starts in the statement, for errors in it
+        let arg_start = start + 1 + name_len + command[name_len..].find(|c: char| !c.is_whitespace()).unwrap_or(0);
+
+        match (name, arg) {
+            ("help", _) => println!("{}", HELP),
+            ("quit", _) => return Flow::Quit,
+            ("vars", _) => self.vars(),
+            ("clear", _) => self.clear(),
+            ("units", "") | ("explain", "") => println!("Usage: :{} <{}> (see :help)", name, if name == "units" { "dimension" } else { "expr" }),
+            ("units", _) => self.units(arg),
+            ("explain", _) => {
+                if let Err(errors) = self.explain(arg) {
+                    errors.iter().for_each(|e| report(&e.clone().offset(arg_start), statement, echo));
+                }
+            },
+            _ => println!("Unknown command ':{}' (see :help).", name),
+        }
+        Flow::Continue
+    }
+
+    fn vars(&self) {
+        let mut variables: Vec<(&str, &Value)> = self.env.variables().collect();
+        let mut functions: Vec<_> = self.env.functions().collect();
+        variables.sort_by_key(|(name, _)| *name);
+        functions.sort_by_key(|(name, _)| *name);
+
+        if variables.is_empty() && functions.is_empty() {
+            println!("No variables or functions yet.");
+        }
+        for (name, value) in variables {
+            println!("{} = {}", name, value);
+        }
+        for (name, function) in functions {
+            println!("{}{}", name, function);
+        }
+    }
+
+    /// Starts over, with just the built-in units.
+    fn clear(&mut self) {
+        let rational_exponents = self.env.rational_exponents;
+        *self.env = Environment::new();
+        self.env.rational_exponents = rational_exponents;
+        println!("Cleared the variables, functions & definitions.");
+    }
+
+    fn units(&self, arg: &str) {
+        let Some(dim) = dimension_of(arg, self.env) else {
+            println!("Unknown dimension '{}' (see :help).", arg);
+            return
+        };
+
+        let registry = &self.env.registry;
+        let base_units = registry.base_units().map(|u| (&u.code, &u.name));
+        let units: Vec<_> = base_units.chain(registry.units().map(|u| (&u.code, &u.name)))
+            .filter(|(code, _)| UnitTerm::new(code, registry).ok().and_then(|t| t.dimension()) == Some(dim))
+            .collect();
+
+        if units.is_empty() {
+            println!("No units of {}.", dim);
+        }
+        for (code, name) in units {
+            println!("{:<16} {}", code, name);
+        }
+    }
+
+    /// Every part of the expression (indented under the one it's part of)
+    /// with its type, then the value (in its units & in base units).
+    fn explain(&self, s: &str) -> Result<(), Vec<CalculumError>> {
+        let expr = expression::parse(s, &self.env.registry).map_err(|e| vec![e])?;
+
+        let mut checker = Checker::new(self.env);
+        let mut rows = vec![];
+        let mut stack = vec![(&expr, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            rows.push((format!("{}{}", "  ".repeat(depth), &s[node.span.clone()]), checker.infer(node)));
+            stack.extend(children(node).into_iter().rev().map(|child| (child, depth + 1)));
+        }
+
+        let width = rows.iter().map(|(text, _)| text.chars().count()).max().unwrap_or(0);
+        for (text, t) in rows {
+            println!("{:<width$}  {}", text, t, width = width);
+        }
+
+        Checker::new(self.env).statement(s)?;
+        let value = expression::eval(&expr, self.env).map_err(|e| vec![e])?;
+        println!("= {}", value);
+        if let Value::Quantity(qn) = &value {
+            let base = qn.term().as_base_units();
+            if qn.term().special().is_none() && qn.term().to_string() != base.to_string() {
+                println!("= {} '{}'", qn.mag() * base.mag, base);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Points at the offending input, below the prompt (or below the
+/// statement, repeated, if it went over more than one line).
+fn report(e: &CalculumError, statement: &str, echo: bool) {
+    if echo {
+        println!("{}{}", " ".repeat(PROMPT.len()), statement);
+    }
+
+    let start = e.span().start.min(statement.len());
+    let end = e.span().end.clamp(start, statement.len());
+    let width = statement[start..end].chars().count().max(1);
+    println!("{}{}", " ".repeat(PROMPT.len() + statement[..start].chars().count()), "^".repeat(width));
+    println!("{}", e);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_open_parentheses() {
+        assert_eq!(open_parens("speed(d: m, t: s"), 1);
+        assert_eq!(open_parens("((1 m + 2 m) * (3"), 2);
+        assert_eq!(open_parens("1 [m/s2/Hz^(1/2)]"), 0);
+        assert_eq!(open_parens("2)"), -1);
+    }
+
+    #[test]
+    fn it_parses_dimensions() {
+        assert_eq!(parse_dimension("L.T-2.M"), Some(DimensionVector::MASS * DimensionVector::LENGTH / DimensionVector::TIME.powi(2)));
+        assert_eq!(parse_dimension("L2.T-5/2"), Some(DimensionVector::LENGTH.powi(2) * DimensionVector::TIME.pow(Rational::new(-5, 2))));
+        assert_eq!(parse_dimension("Θ"), Some(DimensionVector::TEMPERATURE));
+        assert_eq!(parse_dimension("1"), Some(DimensionVector::DIMENSIONLESS));
+        assert_eq!(parse_dimension("m/s"), None);
+        assert_eq!(parse_dimension("T-1/0"), None);
+        assert_eq!(parse_dimension("L.L2"), Some(DimensionVector::LENGTH.powi(3)));
+        assert_eq!(parse_dimension("L2147483647.L"), None);
+    }
+
+    #[test]
+    fn it_finds_dimensions_by_name_and_unit() {
+        let env = Environment::new();
+
+        assert_eq!(dimension_of("L", &env), Some(DimensionVector::LENGTH));
+        assert_eq!(dimension_of("length", &env), Some(DimensionVector::LENGTH));
+        assert_eq!(dimension_of("km/h", &env), Some(DimensionVector::LENGTH / DimensionVector::TIME));
+        assert_eq!(dimension_of("[foo]", &env), None);
+    }
+
+    #[test]
+    fn it_keeps_going_after_errors() {
+        let mut env = Environment::new();
+        let mut repl = Repl::new(&mut env);
+
+        assert!(matches!(repl.handle("1 m + 1 s", false), Flow::Continue));
+        assert!(matches!(repl.handle("x = 2 m", false), Flow::Continue));
+        assert!(matches!(repl.handle(":explain x * (1 s", false), Flow::Continue));
+        assert!(matches!(repl.handle(":quit", false), Flow::Quit));
+        assert!(env.get("x").is_some());
+    }
+
+    #[test]
+    fn it_clears_the_environment() {
+        let mut env = Environment::new();
+        env.rational_exponents = true;
+        let mut repl = Repl::new(&mut env);
+        repl.handle("define [pallet] = 40 [ft_i]3", false);
+        repl.handle("x = 1 [pallet]", false);
+        repl.handle(":clear", false);
+
+        assert!(env.get("x").is_none());
+        assert!(!env.registry.is_atom("[pallet]"));
+        assert!(env.rational_exponents);
+    }
+}
@@ -36,6 +36,8 @@
 //! - This departs from UCUM in one significant way, and that is that `*` is
 //!   parsed as an alternative to `.`, (multiplication) rather than `^`
 //!   (exponentiation). (In what universe does `*` make sense as exponentiation?)
+//!   The one exception is `10*n` (e.g., `10*-7`), which UCUM uses for powers
+//!   of ten and is read the same as `10^n`.
 //!
 //! # Vocabulary
 //!
@@ -57,6 +59,10 @@
 //!   and if their magnitudes are equal after conversion.
 //!
 
+pub mod error;
+pub mod essence;
+pub mod registry;
 pub mod term;
 pub mod unit;
+pub mod special;
 pub mod expression;
\ No newline at end of file
@@ -0,0 +1,237 @@
+//! # Errors
+//! Everything that can go wrong while reading or evaluating an expression.
+//!
+//! Each error records the byte span of the offending input, so callers can
+//! point at it: in `1 kg + 2 k[in_i]`, the prefix error spans `11..18`.
+//!
+use std::{error, fmt, ops::Range};
+
+pub type Span = Range<usize>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalculumError {
+    /// The input doesn't match the grammar (e.g., a missing `)`).
+    Syntax { message: String, span: Span },
+    /// The unit atom isn't part of UCUM (e.g., `[foo]`).
+    UnknownAtom { atom: String, span: Span },
+    /// A prefix on a non-metric atom (e.g., `k[in_i]`).
+    PrefixNotAllowed { prefix: String, atom: String, span: Span },
+    /// The units don't share a dimension (e.g., `1 m + 1 s`).
+    Incommensurable { lhs: String, rhs: String, span: Span },
+    /// The exponent doesn't fit in a unit's exponent (e.g., `m200`).
+    ExponentOverflow { exponent: String, span: Span },
+    DivisionByZero { span: Span },
+    /// A result too large for a float (e.g., `2^10000`).
+    Overflow { span: Span },
+    /// An arbitrary unit converted to anything other than itself (e.g.,
+    /// `[iU]` to `[CFU]`).
+    ArbitraryUnit { unit: String, other: String, span: Span },
+    /// A special unit in a product, quotient or power (e.g., `Cel.m`).
+    SpecialUnit { unit: String, span: Span },
+    /// A special unit in a sum or difference (e.g., `37 Cel + 1 K`, which
+    /// could be `38 Cel` or `311.15 K`).
+    SpecialUnitAddition { unit: String, span: Span },
+    /// A unit defined in terms of a special unit (e.g., `define [warm] =
+    /// 1 Cel`), which isn't a multiple of anything.
+    SpecialUnitDefinition { unit: String, span: Span },
+    /// A definition of an atom or prefix that already exists (e.g., `m`).
+    AlreadyDefined { name: String, span: Span },
+    /// A prefix that would give an atom a second reading (e.g., `ca`, which
+    /// would make `cal` a centi-are-liter).
+    AmbiguousPrefix { prefix: String, unit: String, span: Span },
+    /// A variable that hasn't been assigned (e.g., `accel` before
+    /// `accel = 10 m/s2`).
+    UnknownVariable { name: String, span: Span },
+    /// A power that would leave a unit with a fractional exponent (e.g.,
+    /// `(2 m)^0.5`).
+    FractionalExponent { term: String, exponent: String, span: Span },
+    /// A call to a function that doesn't exist (e.g., `foo(2 m)`).
+    UnknownFunction { name: String, span: Span },
+    /// A value of the wrong type, e.g., the boolean in `(1 m < 2 m) + 1`.
+    TypeMismatch { expected: String, found: String, span: Span },
+    /// A function applied outside its domain (e.g., `ln(0)`).
+    OutOfDomain { function: String, value: String, span: Span },
+}
+
+pub type Result<T> = std::result::Result<T, CalculumError>;
+
+impl CalculumError {
+    pub fn syntax(message: impl Into<String>, span: Span) -> Self {
+        CalculumError::Syntax { message: message.into(), span }
+    }
+
+    pub fn span(&self) -> &Span {
+        use CalculumError::*;
+
+        match self {
+            Syntax { span, .. }
+            | UnknownAtom { span, .. }
+            | PrefixNotAllowed { span, .. }
+            | Incommensurable { span, .. }
+            | ExponentOverflow { span, .. }
+            | DivisionByZero { span }
+            | Overflow { span }
+            | ArbitraryUnit { span, .. }
+            | SpecialUnit { span, .. }
+            | SpecialUnitAddition { span, .. }
+            | SpecialUnitDefinition { span, .. }
+            | AlreadyDefined { span, .. }
+            | AmbiguousPrefix { span, .. }
+            | UnknownVariable { span, .. }
+            | FractionalExponent { span, .. }
+            | UnknownFunction { span, .. }
+            | TypeMismatch { span, .. }
+            | OutOfDomain { span, .. } => span,
+        }
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        use CalculumError::*;
+
+        match self {
+            Syntax { span, .. }
+            | UnknownAtom { span, .. }
+            | PrefixNotAllowed { span, .. }
+            | Incommensurable { span, .. }
+            | ExponentOverflow { span, .. }
+            | DivisionByZero { span }
+            | Overflow { span }
+            | ArbitraryUnit { span, .. }
+            | SpecialUnit { span, .. }
+            | SpecialUnitAddition { span, .. }
+            | SpecialUnitDefinition { span, .. }
+            | AlreadyDefined { span, .. }
+            | AmbiguousPrefix { span, .. }
+            | UnknownVariable { span, .. }
+            | FractionalExponent { span, .. }
+            | UnknownFunction { span, .. }
+            | TypeMismatch { span, .. }
+            | OutOfDomain { span, .. } => span,
+        }
+    }
+
+    /// Shifts the span by `offset` bytes, e.g., to go from a position in a
+    /// unit term to a position in the enclosing expression.
+    pub fn offset(mut self, offset: usize) -> Self {
+        let span = self.span_mut();
+        *span = span.start + offset..span.end + offset;
+        self
+    }
+
+    /// Replaces the span, for errors raised without knowing where the
+    /// input came from (e.g., by `Quantity::convert_to`).
+    pub fn at(mut self, span: Span) -> Self {
+        *self.span_mut() = span;
+        self
+    }
+
+    /// The name of the variant, e.g., `Incommensurable`.
+    pub fn kind(&self) -> &'static str {
+        use CalculumError::*;
+
+        match self {
+            Syntax { .. } => "Syntax",
+            UnknownAtom { .. } => "UnknownAtom",
+            PrefixNotAllowed { .. } => "PrefixNotAllowed",
+            Incommensurable { .. } => "Incommensurable",
+            ExponentOverflow { .. } => "ExponentOverflow",
+            DivisionByZero { .. } => "DivisionByZero",
+            Overflow { .. } => "Overflow",
+            ArbitraryUnit { .. } => "ArbitraryUnit",
+            SpecialUnit { .. } => "SpecialUnit",
+            SpecialUnitAddition { .. } => "SpecialUnitAddition",
+            SpecialUnitDefinition { .. } => "SpecialUnitDefinition",
+            AlreadyDefined { .. } => "AlreadyDefined",
+            AmbiguousPrefix { .. } => "AmbiguousPrefix",
+            UnknownVariable { .. } => "UnknownVariable",
+            FractionalExponent { .. } => "FractionalExponent",
+            UnknownFunction { .. } => "UnknownFunction",
+            TypeMismatch { .. } => "TypeMismatch",
+            OutOfDomain { .. } => "OutOfDomain",
+        }
+    }
+
+    /// What went wrong, without where, e.g., `[Math Error] Division by zero.`
+    pub fn message(&self) -> String {
+        use CalculumError::*;
+
+        match self {
+            Syntax { message, .. } => format!("[Syntax Error] {}", message),
+            UnknownAtom { atom, .. } => format!("[Unit Error] Unknown unit atom '{}'.", atom),
+            PrefixNotAllowed { prefix, atom, .. } => format!(
+                "[Unit Error] The non-metric unit '{}' can't take the prefix '{}'.", atom, prefix
+            ),
+            Incommensurable { lhs, rhs, .. } => format!(
+                "[Dimension Error] Units '{}' and '{}' are not commensurable.", lhs, rhs
+            ),
+            ExponentOverflow { exponent, .. } => format!(
+                "[Unit Error] The exponent '{}' is out of range.", exponent
+            ),
+            DivisionByZero { .. } => "[Math Error] Division by zero.".to_string(),
+            Overflow { .. } => "[Math Error] The result is too large.".to_string(),
+            ArbitraryUnit { unit, other, .. } => format!(
+                "[Unit Error] The arbitrary unit '{}' can only be converted to its prefixed forms, not '{}'.", unit, other
+            ),
+            SpecialUnit { unit, .. } => format!(
+                "[Unit Error] The special unit '{}' can't be multiplied, divided or raised to a power.", unit
+            ),
+            SpecialUnitAddition { unit, .. } => format!(
+                "[Unit Error] The special unit '{}' can't be added or subtracted; convert to its base units first.", unit
+            ),
+            SpecialUnitDefinition { unit, .. } => format!(
+                "[Unit Error] The special unit '{}' can't define another unit.", unit
+            ),
+            AlreadyDefined { name, .. } => format!("[Unit Error] '{}' is already defined.", name),
+            AmbiguousPrefix { prefix, unit, .. } => format!(
+                "[Unit Error] The prefix '{}' would give '{}' a second meaning.", prefix, unit
+            ),
+            UnknownVariable { name, .. } => format!("[Name Error] Unknown variable '{}'.", name),
+            FractionalExponent { term, exponent, .. } => format!(
+                "[Unit Error] Raising '{}' to the power {} leaves a fractional unit exponent.", term, exponent
+            ),
+            UnknownFunction { name, .. } => format!("[Name Error] Unknown function '{}'.", name),
+            TypeMismatch { expected, found, .. } => format!(
+                "[Type Error] Expected a {} but found a {}.", expected, found
+            ),
+            OutOfDomain { function, value, .. } => format!(
+                "[Math Error] '{}' isn't defined for {}.", function, value
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CalculumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at {}..{})", self.message(), self.span().start, self.span().end)
+    }
+}
+
+impl error::Error for CalculumError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_offsets_spans() {
+        let err = CalculumError::DivisionByZero { span: 2..3 }.offset(5);
+
+        assert_eq!(err.span(), &(7..8));
+    }
+
+    #[test]
+    fn it_displays_the_span() {
+        let err = CalculumError::UnknownAtom { atom: "[foo]".to_string(), span: 2..7 };
+
+        assert_eq!(err.to_string(), "[Unit Error] Unknown unit atom '[foo]'. (at 2..7)");
+    }
+
+    #[test]
+    fn it_names_the_kind_of_error() {
+        let err = CalculumError::DivisionByZero { span: 2..3 };
+
+        assert_eq!(err.kind(), "DivisionByZero");
+        assert_eq!(err.message(), "[Math Error] Division by zero.");
+    }
+}
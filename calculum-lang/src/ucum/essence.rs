@@ -0,0 +1,479 @@
+//! # UCUM Essence
+//! The unit tables (prefixes, base units, & everything defined in terms of
+//! them) are read from a vendored copy of UCUM's `ucum-essence.xml` rather
+//! than typed out by hand. To move to a new edition of UCUM, replace
+//! `data/ucum-essence.xml`; the version is recorded in `Essence::version`.
+//!
+//! The file is parsed once, the first time a table is needed.
+//!
+use std::sync::OnceLock;
+
+use crate::ucum::error::{CalculumError, Result};
+
+const ESSENCE_XML: &str = include_str!("../../data/ucum-essence.xml");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefix {
+    pub code: String,
+    pub name: String,
+    pub print_symbol: Option<String>,
+    pub factor: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseUnit {
+    pub code: String,
+    pub name: String,
+    pub print_symbol: Option<String>,
+    pub property: String,
+    /// UCUM's symbol for the dimension (e.g., `L` for length)
+    pub dim: String,
+}
+
+/// The function behind a special unit, e.g., `degF` over `5 K/9`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDef {
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+}
+
+/// `value` times `unit`: `[ft_i]` is `12` times `[in_i]`. (Special units
+/// have a `function` instead, and their `unit` is only for display.)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub unit: String,
+    pub factor: f64,
+    pub function: Option<FunctionDef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitDef {
+    pub code: String,
+    pub name: String,
+    pub print_symbol: Option<String>,
+    pub property: String,
+    pub class: String,
+    pub is_metric: bool,
+    pub is_special: bool,
+    pub is_arbitrary: bool,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Essence {
+    pub version: String,
+    pub revision_date: String,
+    pub prefixes: Vec<Prefix>,
+    pub base_units: Vec<BaseUnit>,
+    pub units: Vec<UnitDef>,
+}
+
+/// The vendored tables.
+pub fn essence() -> &'static Essence {
+    static ESSENCE: OnceLock<Essence> = OnceLock::new();
+
+    ESSENCE.get_or_init(|| {
+        Essence::parse(ESSENCE_XML).unwrap_or_else(|e| panic!("ucum-essence.xml is invalid: {}", e))
+    })
+}
+
+impl Essence {
+    pub fn parse(xml: &str) -> Result<Self> {
+        let root = xml::parse(xml)?;
+        let mut essence = Essence {
+            version: root.attr("version").unwrap_or_default().to_string(),
+            revision_date: root.attr("revision-date").unwrap_or_default().to_string(),
+            prefixes: vec![],
+            base_units: vec![],
+            units: vec![],
+        };
+
+        for el in root.elements() {
+            match el.name.as_str() {
+                "prefix" => essence.prefixes.push(Prefix {
+                    code: el.required_attr("Code")?.to_string(),
+                    name: el.child_text("name").unwrap_or_default(),
+                    print_symbol: el.child_text("printSymbol"),
+                    factor: number(el.required_child("value")?.required_attr("value")?)?,
+                }),
+                "base-unit" => essence.base_units.push(BaseUnit {
+                    code: el.required_attr("Code")?.to_string(),
+                    name: el.child_text("name").unwrap_or_default(),
+                    print_symbol: el.child_text("printSymbol"),
+                    property: el.child_text("property").unwrap_or_default(),
+                    dim: el.required_attr("dim")?.to_string(),
+                }),
+                "unit" => essence.units.push(unit_def(el)?),
+                _ => {}
+            }
+        }
+        Ok(essence)
+    }
+
+    pub fn prefix(&self, code: &str) -> Option<&Prefix> {
+        self.prefixes.iter().find(|p| p.code == code)
+    }
+
+    pub fn base_unit(&self, code: &str) -> Option<&BaseUnit> {
+        self.base_units.iter().find(|u| u.code == code)
+    }
+
+    pub fn unit(&self, code: &str) -> Option<&UnitDef> {
+        self.units.iter().find(|u| u.code == code)
+    }
+
+    pub fn is_atom(&self, code: &str) -> bool {
+        self.base_unit(code).is_some() || self.unit(code).is_some()
+    }
+
+    /// Base units are all metric.
+    pub fn is_metric(&self, code: &str) -> bool {
+        self.base_unit(code).is_some() || self.unit(code).is_some_and(|u| u.is_metric)
+    }
+}
+
+fn number(s: &str) -> Result<f64> {
+    s.parse().map_err(|_| CalculumError::syntax(format!("Invalid number '{}'.", s), 0..0))
+}
+
+fn unit_def(el: &xml::Element) -> Result<UnitDef> {
+    let value = el.required_child("value")?;
+    let function = match value.child("function") {
+        Some(f) => Some(FunctionDef {
+            name: f.required_attr("name")?.to_string(),
+            value: number(f.required_attr("value")?)?,
+            unit: f.required_attr("Unit")?.to_string(),
+        }),
+        None => None,
+    };
+
+    Ok(UnitDef {
+        code: el.required_attr("Code")?.to_string(),
+        name: el.child_text("name").unwrap_or_default(),
+        print_symbol: el.child_text("printSymbol"),
+        property: el.child_text("property").unwrap_or_default(),
+        class: el.attr("class").unwrap_or_default().to_string(),
+        is_metric: el.attr("isMetric") == Some("yes"),
+        is_special: el.attr("isSpecial") == Some("yes"),
+        is_arbitrary: el.attr("isArbitrary") == Some("yes"),
+        value: Value {
+            unit: value.required_attr("Unit")?.to_string(),
+            factor: match value.attr("value") {
+                Some(v) => number(v)?,
+                None => 1.,
+            },
+            function,
+        },
+    })
+}
+
+/// Just enough XML to read the essence: elements, attributes, text, comments,
+/// & character entities (no DTDs, CDATA, or namespaces).
+mod xml {
+    use crate::ucum::error::{CalculumError, Result};
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Node {
+        Element(Element),
+        Text(String),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Element {
+        pub name: String,
+        pub attrs: Vec<(String, String)>,
+        pub children: Vec<Node>,
+    }
+
+    impl Element {
+        pub fn attr(&self, name: &str) -> Option<&str> {
+            self.attrs.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+        }
+
+        pub fn required_attr(&self, name: &str) -> Result<&str> {
+            self.attr(name).ok_or_else(|| CalculumError::syntax(
+                format!("<{}> is missing the attribute '{}'.", self.name, name), 0..0
+            ))
+        }
+
+        pub fn elements(&self) -> impl Iterator<Item = &Element> {
+            self.children.iter().filter_map(|node| match node {
+                Node::Element(el) => Some(el),
+                Node::Text(_) => None,
+            })
+        }
+
+        pub fn child(&self, name: &str) -> Option<&Element> {
+            self.elements().find(|el| el.name == name)
+        }
+
+        pub fn required_child(&self, name: &str) -> Result<&Element> {
+            self.child(name).ok_or_else(|| CalculumError::syntax(
+                format!("<{}> is missing the element <{}>.", self.name, name), 0..0
+            ))
+        }
+
+        /// The text of a child, without any markup (`10<sup>3</sup>` is `103`).
+        pub fn child_text(&self, name: &str) -> Option<String> {
+            self.child(name).map(|el| el.text().trim().to_string())
+        }
+
+        pub fn text(&self) -> String {
+            self.children.iter().map(|node| match node {
+                Node::Element(el) => el.text(),
+                Node::Text(s) => s.clone(),
+            }).collect()
+        }
+    }
+
+    struct Reader<'a> {
+        s: &'a str,
+        pos: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn rest(&self) -> &'a str {
+            &self.s[self.pos..]
+        }
+
+        fn error(&self, message: impl Into<String>) -> CalculumError {
+            CalculumError::syntax(message, self.pos..self.pos)
+        }
+
+        fn skip_whitespace(&mut self) {
+            let rest = self.rest();
+            self.pos += rest.len() - rest.trim_start().len();
+        }
+
+        /// Skips past the next `end`.
+        fn skip_past(&mut self, end: &str) -> Result<()> {
+            match self.rest().find(end) {
+                Some(i) => {
+                    self.pos += i + end.len();
+                    Ok(())
+                },
+                None => Err(self.error(format!("Expected '{}'.", end))),
+            }
+        }
+
+        /// Skips the declaration (`<?xml ...?>`) & comments.
+        fn skip_misc(&mut self) -> Result<()> {
+            loop {
+                self.skip_whitespace();
+                if self.rest().starts_with("<?") {
+                    self.skip_past("?>")?;
+                } else if self.rest().starts_with("<!--") {
+                    self.skip_past("-->")?;
+                } else {
+                    return Ok(())
+                }
+            }
+        }
+
+        fn name(&mut self) -> Result<String> {
+            let rest = self.rest();
+            let len = rest.find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+                .unwrap_or(rest.len());
+
+            if len == 0 {
+                return Err(self.error("Expected a name."))
+            }
+            self.pos += len;
+            Ok(rest[..len].to_string())
+        }
+
+        fn element(&mut self) -> Result<Element> {
+            if !self.rest().starts_with('<') {
+                return Err(self.error("Expected an element."))
+            }
+            self.pos += 1;
+            let name = self.name()?;
+            let mut el = Element { name, attrs: vec![], children: vec![] };
+
+            loop {
+                self.skip_whitespace();
+                let rest = self.rest();
+
+                if rest.starts_with("/>") {
+                    self.pos += 2;
+                    return Ok(el)
+                } else if rest.starts_with('>') {
+                    self.pos += 1;
+                    break
+                }
+
+                let key = self.name()?;
+                if !self.rest().starts_with("=\"") {
+                    return Err(self.error(format!("Expected a quoted value for '{}'.", key)))
+                }
+                self.pos += 2;
+                let len = self.rest().find('"').ok_or_else(|| self.error("Unterminated attribute."))?;
+                let value = unescape(&self.rest()[..len]);
+                self.pos += len + 1;
+                el.attrs.push((key, value));
+            }
+
+            loop {
+                let rest = self.rest();
+
+                if rest.starts_with("</") {
+                    self.pos += 2;
+                    let end = self.name()?;
+                    if end != el.name {
+                        return Err(self.error(format!("Expected </{}>, found </{}>.", el.name, end)))
+                    }
+                    self.skip_whitespace();
+                    self.skip_past(">")?;
+                    return Ok(el)
+                } else if rest.starts_with("<!--") {
+                    self.skip_past("-->")?;
+                } else if rest.starts_with('<') {
+                    el.children.push(Node::Element(self.element()?));
+                } else if rest.is_empty() {
+                    return Err(self.error(format!("Unclosed element <{}>.", el.name)))
+                } else {
+                    let len = rest.find('<').unwrap_or(rest.len());
+                    el.children.push(Node::Text(unescape(&rest[..len])));
+                    self.pos += len;
+                }
+            }
+        }
+    }
+
+    fn unescape(s: &str) -> String {
+        let mut result = String::with_capacity(s.len());
+        let mut rest = s;
+
+        while let Some(i) = rest.find('&') {
+            result.push_str(&rest[..i]);
+            rest = &rest[i..];
+
+            let entity = rest.find(';').map(|end| (&rest[1..end], end));
+            let c = entity.and_then(|(name, _)| match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ if name.starts_with("#x") => u32::from_str_radix(&name[2..], 16).ok().and_then(char::from_u32),
+                _ if name.starts_with('#') => name[1..].parse().ok().and_then(char::from_u32),
+                _ => None,
+            });
+
+            match (c, entity) {
+                (Some(c), Some((_, end))) => {
+                    result.push(c);
+                    rest = &rest[end + 1..];
+                },
+                // Leave unknown entities as they are
+                _ => {
+                    result.push('&');
+                    rest = &rest[1..];
+                },
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Reads the root element.
+    pub fn parse(s: &str) -> Result<Element> {
+        let mut reader = Reader { s, pos: 0 };
+
+        reader.skip_misc()?;
+        let root = reader.element()?;
+        reader.skip_misc()?;
+
+        if !reader.rest().is_empty() {
+            return Err(reader.error("Expected the end of the document."))
+        }
+        Ok(root)
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn it_reads_elements_attributes_and_text() {
+            let root = parse(r#"<?xml version="1.0"?>
+                <!-- a comment -->
+                <root version="2.1">
+                    <unit Code="[in_i]" isMetric="no"><name>inch</name><printSymbol>10<sup>2</sup></printSymbol></unit>
+                    <empty/>
+                </root>"#).unwrap();
+
+            let unit = root.child("unit").unwrap();
+
+            assert_eq!(root.attr("version"), Some("2.1"));
+            assert_eq!(unit.attr("Code"), Some("[in_i]"));
+            assert_eq!(unit.child_text("name").as_deref(), Some("inch"));
+            assert_eq!(unit.child_text("printSymbol").as_deref(), Some("102"));
+            assert!(root.child("empty").is_some());
+        }
+
+        #[test]
+        fn it_unescapes_entities() {
+            assert_eq!(unescape("&lt;&amp;&gt; &#176;F &#xB0;C &foo;"), "<&> °F °C &foo;");
+        }
+
+        #[test]
+        fn it_rejects_mismatched_tags() {
+            assert!(parse("<a><b></a></b>").is_err());
+            assert!(parse("<a>").is_err());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_records_the_version() {
+        assert_eq!(essence().version, "2.1");
+        assert_eq!(essence().revision_date, "2017-11-21");
+    }
+
+    #[test]
+    fn it_reads_every_table() {
+        assert_eq!(essence().prefixes.len(), 24);
+        assert_eq!(essence().base_units.len(), 7);
+        assert_eq!(essence().prefix("u").unwrap().factor, 1e-6);
+        assert_eq!(essence().base_unit("K").unwrap().property, "temperature");
+    }
+
+    #[test]
+    fn it_keeps_each_units_metadata() {
+        let cel = essence().unit("Cel").unwrap();
+
+        assert_eq!(cel.name, "degree Celsius");
+        assert_eq!(cel.print_symbol.as_deref(), Some("°C"));
+        assert_eq!(cel.property, "temperature");
+        assert_eq!(cel.class, "si");
+        assert!(cel.is_metric && cel.is_special && !cel.is_arbitrary);
+        assert_eq!(
+            cel.value.function,
+            Some(FunctionDef { name: "Cel".to_string(), value: 1., unit: "K".to_string() })
+        );
+
+        let iu = essence().unit("[iU]").unwrap();
+        assert!(iu.is_arbitrary && !iu.is_special);
+    }
+
+    #[test]
+    fn it_reads_the_values_exactly() {
+        let value = |code| {
+            let def = essence().unit(code).unwrap();
+            (def.value.factor, def.value.unit.as_str())
+        };
+
+        assert_eq!(value("%"), (1., "10*-2"));
+        assert_eq!(value("[e]"), (1.60217733e-19, "C"));
+        assert_eq!(value("[G]"), (6.67259e-11, "m3.kg-1.s-2"));
+        assert_eq!(value("[ft_i]"), (12., "[in_i]"));
+    }
+}
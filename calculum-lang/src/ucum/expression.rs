@@ -1,99 +1,308 @@
 //! # Expressions
 //!
+//! Errors (syntax, unknown units, incommensurable operands, ...) are returned
+//! as `CalculumError`s with the span of the offending input.
+//!
 //! ## Todos
-//! - [ ] Support for parentheses (`(...)`).
-//! - [ ] Support for exponentiation (`a^b`).
-//! - [ ] Support for comparisons (binary & unary). Departure from excel -> `==` & `!=`
-//!   - [ ] Support for assessing commensurability (`~~`).
+//! - [x] Support for parentheses (`(...)`), with the usual precedence
+//!   (`^`, then unary `-`, then `*` & `/`, then `+` & `-`, then `->`).
+//! - [x] Support for exponentiation (`a^b`) & roots (`sqrt(a)`).
+//! - [x] Support for rational unit exponents (`V/Hz^(1/2)`).
+//! - [x] Support for Excel-style functions (`SUM`, `ROUND`, `IF`, ...).
+//! - [x] Support for transcendental functions (`sin`, `exp`, `ln`, ...).
+//! - [x] Support for defining functions (`speed(d: m, t: s): m/s = d / t`).
+//! - [x] Support for comparisons (`==`, `!=`, `<`, `<=`, `>`, `>=`).
+//!   Departure from excel -> `==` & `!=`
+//!   - [x] Support for assessing commensurability (`~~`).
 //! - [ ] Support for strings (`'...'`, `"..."`).
 //! - [ ] Support for string concatenation (`a & b`).
-//! - [ ] Support for variable assignmet.
-//! - [ ] Support for unit terms with unary division (`/s`)
-//! - [ ] Support for automatic conversions.
+//! - [x] Support for variable assignment (`accel = 10 m/s2`).
+//! - [x] Support for unit terms with unary division (`10/s`)
+//! - [x] Support for conversions (`10 m/s2 -> [mi_i]/h2` or `3 [ft_i] in cm`).
+//! - [x] Support for defining units (`define [pallet] = 40 [ft_i]3`) & base
+//!   dimensions (`dimension [currency]`).
+//! - [x] Support for checking dimensions before evaluating (`check`).
 //!
-use std::{fmt, ops};
-use crate::ucum::term;
+use std::fmt;
+use std::collections::HashMap;
+
+use calculum_core::rational::Rational;
+
+use crate::ucum::error::{CalculumError, Result, Span};
+use crate::ucum::registry::UnitRegistry;
+use crate::ucum::term::{self, ReducibleUnit};
 
 
 #[derive(PartialEq, Clone)]
 pub struct Quantity {
     mag: f64,
-    term: Option<term::UnitTerm>
+    term: term::UnitTerm
 }
 
 impl Quantity {
-    pub fn new(mag: f64, t: &str) -> Self {
-        Quantity {
+    pub fn mag(&self) -> f64 {
+        self.mag
+    }
+
+    /// The unit term, as written (`km/h` stays `km/h`).
+    pub fn term(&self) -> &term::UnitTerm {
+        &self.term
+    }
+
+    pub fn new(mag: f64, t: &str, registry: &UnitRegistry) -> Result<Self> {
+        Ok(Quantity {
             mag,
-            term: term::UnitTerm::new(t),
-        }
+            term: term::UnitTerm::new(t, registry)?,
+        })
     }
-}
 
-impl ops::AddAssign for Quantity {
-    fn add_assign(&mut self, other: Self) {
-        self.mag += other.mag;
-        match &mut self.term {
-            Some(t) => *t += other.term.unwrap(),
-            None => assert!(other.term.is_none())
+    /// Raises the quantity to a power (`(3 m)^2` is `9 m2`).
+    pub fn pow(&self, exp: Rational) -> Result<Quantity> {
+        let mut term = self.term.pow(exp)?;
+        Ok(Quantity { mag: exp.power_of(self.mag) * term.take_factor(), term })
+    }
+
+    /// Expresses the quantity in the `target` unit term, which is kept as
+    /// written (`10 m/s2` in `[mi_i]/h2` is `80529.71 [mi_i]/h2`).
+    ///
+    /// The error's span is empty, since the quantity doesn't know where
+    /// it came from.
+    pub fn convert_to(&self, target: &term::UnitTerm) -> Result<Quantity> {
+        // Special units convert by way of their ratio units (`Cel` via `K`)
+        if let Some(special) = self.term.special() {
+            let ratio = Quantity { mag: special.to_ratio(self.mag), term: special.ratio_term()? };
+            return ratio.convert_to(target)
+        }
+        if let Some(special) = target.special() {
+            let ratio = self.convert_to(&special.ratio_term()?)?;
+            return Ok(Quantity { mag: special.from_ratio(ratio.mag), term: target.clone() })
+        }
+
+        let from = self.term.as_base_units();
+        let to = target.as_base_units();
+
+        if from.units != to.units {
+            if let Some(err) = arbitrary_unit_error(&self.term, target, 0..0) {
+                return Err(err)
+            }
+            return Err(CalculumError::Incommensurable {
+                lhs: self.term.dimension_name(),
+                rhs: target.dimension_name(),
+                span: 0..0
+            })
         }
+
+        Ok(Quantity {
+            mag: self.mag * from.mag / to.mag,
+            term: target.clone()
+        })
+    }
+
+    /// Converts `other` to our unit term, then adds the magnitudes
+    /// (`1 km + 1 m` is `1.001 km`). Special units don't add up: `37 Cel
+    /// + 1 K` could mean either of two temperatures.
+    ///
+    /// The error's span is empty, as in `convert_to`.
+    pub fn checked_add(mut self, other: &Quantity) -> Result<Quantity> {
+        self.mag += self.addend(other)?;
+        Ok(self)
+    }
+
+    pub fn checked_sub(mut self, other: &Quantity) -> Result<Quantity> {
+        self.mag -= self.addend(other)?;
+        Ok(self)
+    }
+
+    /// The product, which fails if a unit's exponent goes out of range
+    /// (see `UnitTerm::checked_mul`).
+    pub fn checked_mul(self, other: &Quantity) -> Result<Quantity> {
+        let mut term = self.term.checked_mul(&other.term)?;
+        let mag = self.mag * other.mag * term.take_factor();
+        Ok(Quantity { mag, term })
     }
-}
 
-impl ops::SubAssign for Quantity {
-    fn sub_assign(&mut self, other: Self) {
-        self.mag -= other.mag;
-        match &mut self.term {
-            Some(t) => *t -= other.term.unwrap(),
-            None => assert!(other.term.is_none())
+    pub fn checked_div(self, other: &Quantity) -> Result<Quantity> {
+        let mut term = self.term.checked_div(&other.term)?;
+        let mag = self.mag / other.mag * term.take_factor();
+        Ok(Quantity { mag, term })
+    }
+
+    /// `other`'s magnitude in our unit term.
+    fn addend(&self, other: &Quantity) -> Result<f64> {
+        let special = [&self.term, &other.term].into_iter().find(|term| term.special().is_some());
+        if let Some(term) = special {
+            return Err(CalculumError::SpecialUnitAddition { unit: term.to_string(), span: 0..0 })
         }
+        Ok(other.convert_to(&self.term)?.mag)
     }
 }
 
-impl ops::MulAssign for Quantity {
-    fn mul_assign(&mut self, other: Self) {
-        self.mag *= other.mag;
-        match &mut self.term {
-            Some(t) => *t *= other.term.unwrap(),
-            None => assert!(other.term.is_none())
+/// A parameter of a user-defined function, with the unit its arguments
+/// are converted to (if it has one).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub unit: Option<term::UnitTerm>,
+}
+
+/// A user-defined function, e.g., `speed(d: m, t: s): m/s = d / t`.
+/// Arguments are converted to the parameters' units on the way in, and
+/// the result to the function's unit on the way out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub params: Vec<Param>,
+    pub unit: Option<term::UnitTerm>,
+    pub body: parser::Expr,
+}
+
+impl fmt::Display for Function {
+    /// The signature: `(d: m, t: s): m/s`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self.params.iter()
+            .map(|param| match &param.unit {
+                Some(unit) => format!("{}: {}", param.name, unit),
+                None => param.name.clone(),
+            })
+            .collect();
+
+        write!(f, "({})", params.join(", "))?;
+        match &self.unit {
+            Some(unit) => write!(f, ": {}", unit),
+            None => Ok(()),
         }
     }
 }
 
-impl ops::DivAssign for Quantity {
-    fn div_assign(&mut self, other: Self) {
-        self.mag /= other.mag;
-        match &mut self.term {
-            Some(t) => *t /= other.term.unwrap(),
-            None => assert!(other.term.is_none())
-        }
+/// What statements can refer to: the units (which `define` & `dimension`
+/// add to), the variables (which assignments add to) and the functions.
+/// Variables live apart from units, so `m = 3 s` doesn't change what `2 m`
+/// means.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    pub registry: UnitRegistry,
+    variables: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+    /// Whether powers may leave units with fractional exponents (e.g.,
+    /// `sqrt(2 m)`), which are otherwise an error.
+    pub rational_exponents: bool,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub fn define_function(&mut self, name: &str, function: Function) {
+        self.functions.insert(name.to_string(), function);
+    }
+
+    /// The variables, in no particular order.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.variables.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// The user-defined functions, in no particular order.
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &Function)> {
+        self.functions.iter().map(|(name, function)| (name.as_str(), function))
     }
 }
 
+/// Arbitrary units get an error of their own when they don't convert, since
+/// they don't have a dimension to compare.
+fn arbitrary_unit_error(lhs: &term::UnitTerm, rhs: &term::UnitTerm, span: Span) -> Option<CalculumError> {
+    let (unit, other) = match (lhs.is_arbitrary(), rhs.is_arbitrary()) {
+        (true, _) => (lhs, rhs),
+        (_, true) => (rhs, lhs),
+        _ => return None,
+    };
+    Some(CalculumError::ArbitraryUnit { unit: unit.to_string(), other: other.to_string(), span })
+}
+
+
 
 impl fmt::Display for Quantity {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.term {
-            Some(t) => write!(f, "{} '{}'", self.mag, t),
-            None => write!(f, "{}", self.mag),
-        }
+        write!(f, "{} '{}'", self.mag, self.term)
     }
 }
 
 impl fmt::Debug for Quantity {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.term {
-            Some(t) => write!(f, "{} '{:?}'", self.mag, t),
-            None => write!(f, "{}", self.mag),
+        write!(f, "{} '{:?}'", self.mag, self.term)
+    }
+}
+
+/// What an expression evaluates to: a quantity, or the boolean result of
+/// a comparison (`1 km > 1 m`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Quantity(Quantity),
+    Bool(bool),
+}
+
+impl Value {
+    /// The name of the value's type, for errors.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Quantity(_) => "quantity",
+            Value::Bool(_) => "boolean",
+        }
+    }
+
+    pub fn as_quantity(&self) -> Option<&Quantity> {
+        match self {
+            Value::Quantity(qn) => Some(qn),
+            Value::Bool(_) => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            Value::Quantity(_) => None,
+        }
+    }
+}
+
+impl From<Quantity> for Value {
+    fn from(qn: Quantity) -> Self {
+        Value::Quantity(qn)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Quantity(qn) => write!(f, "{}", qn),
+            Value::Bool(b) => write!(f, "{}", b),
         }
     }
 }
 
 pub mod tokenizer {
     use std::fmt;
-    use std::collections::VecDeque;
 
-    use crate::ucum::{unit, term};
+    use crate::ucum::error::{CalculumError, Result, Span};
+    use crate::ucum::registry::UnitRegistry;
+    use crate::ucum::term::UnitTerm;
     use super::Quantity;
 
     #[derive(PartialEq)]
@@ -102,7 +311,26 @@ pub mod tokenizer {
         Sub,
         Mul,
         Div,
-        Quantity(super::Quantity)
+        /// `->` or `in`, always followed by a `Term`
+        Convert,
+        Pow,
+        LParen,
+        RParen,
+        Quantity(super::Quantity),
+        Term(UnitTerm),
+        /// A variable (anywhere but after a magnitude or `->`, where
+        /// letters start a unit term instead)
+        Ident(String),
+        /// Separates the arguments of a function call
+        Comma,
+        Eq,
+        Ne,
+        Lt,
+        Le,
+        Gt,
+        Ge,
+        /// `~~`, which may be followed by a `Term` (`1 km/h ~~ m/s`)
+        Commensurable,
     }
 
     impl fmt::Debug for Token {
@@ -112,264 +340,2944 @@ pub mod tokenizer {
                 Self::Sub => write!(f, "-"),
                 Self::Mul => write!(f, "*"),
                 Self::Div => write!(f, "/"),
+                Self::Convert => write!(f, "->"),
+                Self::Pow => write!(f, "^"),
+                Self::LParen => write!(f, "("),
+                Self::RParen => write!(f, ")"),
                 Self::Quantity(q) => write!(f, "({:?})", q),
+                Self::Term(t) => write!(f, "'{:?}'", t),
+                Self::Ident(name) => write!(f, "{}", name),
+                Self::Comma => write!(f, ","),
+                Self::Eq => write!(f, "=="),
+                Self::Ne => write!(f, "!="),
+                Self::Lt => write!(f, "<"),
+                Self::Le => write!(f, "<="),
+                Self::Gt => write!(f, ">"),
+                Self::Ge => write!(f, ">="),
+                Self::Commensurable => write!(f, "~~"),
             }
         }
     }
 
-    #[derive(Debug, )]
+    pub fn starts_ident(c: char) -> bool {
+        c.is_alphabetic() || c == '_'
+    }
+
+    pub fn is_ident_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '_'
+    }
+
+    /// Characters that can start a unit term after the magnitude & a space.
+    /// (`/` only starts a term when attached to the magnitude, as in `10/s`,
+    /// since `10 / 2` is a division. See `Tokenizer::at_other_term` for
+    /// terms starting with `10^` or `(`.)
+    fn starts_term(c: char) -> bool {
+        c.is_alphabetic() || matches!(c, '[' | '{' | '%' | '\'' | '"' | '_')
+    }
+
+    #[derive(Debug, Clone)]
     pub struct Tokenizer<'a> {
-        chars: std::str::Chars<'a>,
-        visited: VecDeque<char>,
+        s: &'a str,
+        // Byte offset of the next character
+        pos: usize,
+        // Whether we just read a conversion (so what follows is a unit term)
+        after_convert: bool,
+        // Whether we just read a `~~` (so letters start a unit term, as
+        // they do right before one)
+        after_commensurable: bool,
+        registry: &'a UnitRegistry,
     }
 
     impl<'a> Tokenizer<'a> {
-        pub fn new(s: &'a str) -> Self {
+        pub fn new(s: &'a str, registry: &'a UnitRegistry) -> Self {
             Tokenizer {
-                chars: s.chars(),
-                visited: VecDeque::new(),
+                s,
+                pos: 0,
+                after_convert: false,
+                after_commensurable: false,
+                registry,
             }
         }
 
-        fn visited_to_mag(&mut self) -> f64 {
-            let mag: f64 = self.visited.iter().collect::<String>().parse().unwrap();
-            self.visited.clear();
-            mag
+        /// Whether `keyword` comes next, as a word of its own.
+        fn at_keyword(&self, keyword: &str) -> bool {
+            let rest = &self.s[self.pos..];
+            rest.starts_with(keyword) && rest[keyword.len()..].chars().next().is_none_or(|c| c.is_ascii_whitespace())
         }
 
-        fn next_mag(&mut self) -> f64 {
-            loop {
-                match self.chars.next() {
-                    Some(c) => match c {
-                        '.' | '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                            self.visited.push_back(c)
-                        },
-                        _ => {
-                            let mag = self.visited_to_mag();
-                            self.visited.push_back(c);
-                            return mag
-                        }
-                    },
-                    None => {
-                        return self.visited_to_mag()
-                    }
-                }
+        /// Whether a unit term comes next, followed by `~~` (`m ~~ s`), so
+        /// that both sides of `~~` read the same way. Names followed by `(`
+        /// are still calls.
+        fn at_term_before_commensurable(&self) -> bool {
+            if self.s[self.pos..].trim_start_matches(is_ident_char).starts_with('(') {
+                return false
             }
+            let mut ahead = self.clone();
+            matches!(ahead.next_term(), Ok(term) if !term.is_empty())
+                && ahead.s[ahead.pos..].trim_start().starts_with("~~")
         }
 
-        /// This is redundant with `term::UnitTerm.next_annotation()`.
-        /// Except for within an annotation, we're not allowed to use
-        /// whitespace in unit terms.
-        fn next_annotation(&mut self) {
-            loop {
-                match self.chars.next() {
-                    Some(c) => match c {
-                        '}' => {
-                            self.visited.push_back('}');
-                            break
-                        },
-                        _ => self.visited.push_back(c)
-                    },
-                    None => break
-                }
+        /// Whether a unit term that doesn't start like a name comes next,
+        /// after a magnitude & a space: one with a power of ten (`1 10^3.kg`)
+        /// or in parentheses (`1 (m)`, but not `2 (3 + 4)`).
+        fn at_other_term(&self) -> bool {
+            let rest = &self.s[self.pos..];
+            if let Some(exp) = rest.strip_prefix("10^").or_else(|| rest.strip_prefix("10*")) {
+                return exp.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-')
             }
+            let mut ahead = self.clone();
+            rest.starts_with('(') && matches!(ahead.next_term(), Ok(term) if UnitTerm::new(term, self.registry).is_ok())
+        }
+
+        fn peek(&self) -> Option<char> {
+            self.s[self.pos..].chars().next()
         }
 
-        fn next_term(&mut self) -> String {
-            if self.visited[0] == ' ' {
-                self.visited.pop_front();
+        fn bump_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+            let start = self.pos;
+            while let Some(c) = self.peek().filter(|&c| f(c)) {
+                self.pos += c.len_utf8();
             }
-            loop {
-                match self.chars.next() {
-                    Some(c) => match c {
-                        ' ' => break,
-                        '{' => self.next_annotation(),
-                        _ => self.visited.push_back(c),
+            &self.s[start..self.pos]
+        }
+
+        fn next_mag(&mut self) -> Result<f64> {
+            let start = self.pos;
+            let mag = self.bump_while(|c| c == '.' || c.is_ascii_digit());
+
+            mag.parse().map_err(|_| CalculumError::syntax(
+                format!("Invalid number '{}'.", mag), start..self.pos
+            ))
+        }
+
+        /// Except for within an annotation, we're not allowed to use
+        /// whitespace in unit terms. A `)` the term didn't open closes a
+        /// group around it (`(3 s + 1 min)`), a `,` ends an argument, and
+        /// a `~` starts a `~~`.
+        fn next_term(&mut self) -> Result<&'a str> {
+            let start = self.pos;
+            let mut depth = 0;
+            while let Some(c) = self.peek() {
+                match c {
+                    ',' | '~' => break,
+                    c if c.is_ascii_whitespace() => break,
+                    ')' if depth == 0 => break,
+                    '{' => { self.bump_while(|c| c != '}'); },
+                    _ => {
+                        match c {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => {},
+                        }
+                        self.pos += c.len_utf8();
                     },
-                    None => break
                 }
             }
-            let term = self.visited.iter().collect::<String>();
-            self.visited.clear();
-            term
+            Ok(&self.s[start..self.pos])
         }
 
-        pub fn next_quantity(&mut self, c_0: char) -> Option<Token> {
-            self.visited.push_back(c_0);
-            let mag: f64 = self.next_mag();
-            let maybe_term: String = self.next_term();
-            Some(Token::Quantity(Quantity::new(mag, &maybe_term)))
-        }
-    }
+        pub fn next_quantity(&mut self) -> Result<Token> {
+            let mag: f64 = self.next_mag()?;
 
-    impl<'a> Iterator for Tokenizer<'a> {
-        type Item = Token;
+            let attached = matches!(self.peek(), Some('/' | '('));
+            let spaces = self.bump_while(|c| c.is_ascii_whitespace()).len();
+            let start = self.pos;
 
-        fn next(&mut self) -> Option<Self::Item> {
-            let maybe_c = self.visited
-                .pop_front()
-                .or_else(|| self.chars.next());
-
-            match maybe_c {
-                Some(c) => match c {
-                    '+' => Some(Token::Add),
-                    '-' => Some(Token::Sub),
-                    '*' => Some(Token::Mul),
-                    '/' => Some(Token::Div),
-                    '.' | '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9'
-                        => self.next_quantity(c),
-                    ' ' => self.next(),
-                    _ => panic!("[Syntax Error] Encountered an invalid token {:?}.", c)
+            let term = match self.peek() {
+                Some(_) if self.at_keyword("in") => {
+                    self.pos -= spaces;
+                    ""
                 },
-                None => None
+                Some(c) if starts_term(c) || attached || spaces > 0 && self.at_other_term() => self.next_term()?,
+                _ => {
+                    // Leave the whitespace for the next token
+                    self.pos -= spaces;
+                    ""
+                }
+            };
+
+            Quantity::new(mag, term, self.registry)
+                .map(Token::Quantity)
+                .map_err(|e| e.offset(start))
+        }
+
+        pub fn next_unit_term(&mut self) -> Result<Token> {
+            let start = self.pos;
+            let term = self.next_term()?;
+
+            if term.is_empty() {
+                return Err(CalculumError::syntax(
+                    "Expected a unit term to convert to.", start..start
+                ))
             }
+
+            UnitTerm::new(term, self.registry)
+                .map(Token::Term)
+                .map_err(|e| e.offset(start))
         }
     }
-}
 
-mod interpreter {
-    use super::{tokenizer, Quantity};
-    use super::tokenizer::Token;
+    impl<'a> Iterator for Tokenizer<'a> {
+        type Item = Result<(Token, Span)>;
 
-    pub fn interpret(s: &str) -> Quantity {
-        let mut ts: tokenizer::Tokenizer = tokenizer::Tokenizer::new(s);
+        fn next(&mut self) -> Option<Self::Item> {
+            self.bump_while(|c| c.is_ascii_whitespace());
+            let start = self.pos;
 
-        let next_qn = |ts: &mut tokenizer::Tokenizer, err: &str| {
-            match ts.next().unwrap() {
-                Token::Quantity(qn) => qn,
-                _ => panic!("{}", err)
+            if self.after_convert {
+                self.after_convert = false;
+                return Some(self.next_unit_term().map(|t| (t, start..self.pos)))
             }
-        };
 
-        let mut qn = next_qn(
-            &mut ts, "[Syntax Error] Your statement must begin with a quantity."
-        );
+            let c = self.peek()?;
 
-        let err = "[Syntax Error] Two quantities must be separated by one of the following tokens: '+', '-', '*', '/'";
+            if std::mem::take(&mut self.after_commensurable) && starts_term(c)
+                || starts_term(c) && self.at_term_before_commensurable() {
+                return Some(self.next_unit_term().map(|t| (t, start..self.pos)))
+            }
 
-        loop {
-            match ts.next() {
-                Some(t) => match t {
-                    Token::Add => { qn += next_qn(&mut ts, err) },
-                    Token::Sub => { qn -= next_qn(&mut ts, err) },
-                    Token::Mul => { qn *= next_qn(&mut ts, err) },
-                    Token::Div => { qn /= next_qn(&mut ts, err) },
-                    Token::Quantity(qn) => panic!("{}", err)
-                },
-                None => break
+            if self.s[self.pos..].starts_with("->") || self.at_keyword("in") {
+                self.pos += 2;
+                self.after_convert = true;
+                return Some(Ok((Token::Convert, start..self.pos)))
+            }
+
+            let comparison = match self.s[self.pos..].get(..2) {
+                Some("==") => Some(Token::Eq),
+                Some("!=") => Some(Token::Ne),
+                Some("<=") => Some(Token::Le),
+                Some(">=") => Some(Token::Ge),
+                Some("~~") => Some(Token::Commensurable),
+                _ => None,
+            };
+            if let Some(token) = comparison {
+                self.pos += 2;
+                self.after_commensurable = token == Token::Commensurable;
+                return Some(Ok((token, start..self.pos)))
             }
+
+            let token = match c {
+                '+' => Ok(Token::Add),
+                '-' => Ok(Token::Sub),
+                '*' => Ok(Token::Mul),
+                '/' => Ok(Token::Div),
+                '^' => Ok(Token::Pow),
+                '(' => Ok(Token::LParen),
+                ')' => Ok(Token::RParen),
+                ',' => Ok(Token::Comma),
+                '<' => Ok(Token::Lt),
+                '>' => Ok(Token::Gt),
+                '=' => Err(CalculumError::syntax(
+                    "'=' only assigns to a variable (`x = 2 m`); compare with '=='.", start..start + 1
+                )),
+                '.' | '0'..='9' => {
+                    return Some(self.next_quantity().map(|t| (t, start..self.pos)))
+                },
+                // Can't start a name, so it's a unit term (`[pi]`, `%`), as
+                // in `ROUND(1 [mi_i], 2, [ft_i])`
+                '[' | '{' | '%' => {
+                    return Some(self.next_unit_term().map(|t| (t, start..self.pos)))
+                },
+                c if starts_ident(c) => {
+                    let name = self.bump_while(is_ident_char);
+                    return Some(Ok((Token::Ident(name.to_string()), start..self.pos)))
+                },
+                _ => Err(CalculumError::syntax(
+                    format!("Encountered an invalid token {:?}.", c), start..start + c.len_utf8()
+                ))
+            };
+
+            self.pos += c.len_utf8();
+            Some(token.map(|t| (t, start..self.pos)))
         }
-        qn
     }
 }
 
-pub use interpreter::interpret;
+pub mod parser {
+    use std::iter::Peekable;
+    use std::vec::IntoIter;
 
+    use super::tokenizer::{Token, Tokenizer};
+    use super::Quantity;
+    use crate::ucum::error::{CalculumError, Result, Span};
+    use crate::ucum::term::UnitTerm;
 
-#[cfg(test)]
-mod tests {
-    use crate::ucum::{unit, term};
-    use super::*;
-
-    fn tokens(s: &str) -> Vec<tokenizer::Token> {
-        tokenizer::Tokenizer::new(s).collect()
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum BinOp {
+        Add,
+        Sub,
+        Mul,
+        Div,
+        Pow,
+        Eq,
+        Ne,
+        Lt,
+        Le,
+        Gt,
+        Ge,
+        /// `~~`: whether the operands share a dimension
+        Commensurable,
     }
 
-    fn quantity(mag: f64, term: &str) -> tokenizer::Token {
-        tokenizer::Token::Quantity(Quantity::new(mag, term))
+    impl BinOp {
+        /// Whether the operator evaluates to a boolean.
+        pub fn is_comparison(&self) -> bool {
+            use BinOp::*;
+
+            matches!(self, Eq | Ne | Lt | Le | Gt | Ge | Commensurable)
+        }
     }
 
-    #[test]
-    fn it_reads_the_next_quantity() {
-        assert_eq!(tokens("1 kg.m/s2")[0], quantity(1., "kg.m/s2"));
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ExprKind {
+        Quantity(Quantity),
+        Variable(String),
+        Neg(Box<Expr>),
+        Binary(BinOp, Box<Expr>, Box<Expr>),
+        /// A function call, e.g., `sqrt(9 m2)`
+        Call(String, Vec<Expr>),
+        Convert(Box<Expr>, UnitTerm),
+        /// A unit term on its own, as in `1 km/h ~~ m/s` or `[pi] * 2`
+        /// (where it stands for one of itself)
+        Term(UnitTerm),
     }
 
-    #[test]
-    fn it_ignores_spaces_bw_mag_and_term() {
-        assert_eq!(tokens("1 kg.m/s2"), tokens("1kg.m/s2"));
+    /// A node of the syntax tree, with the span of input it was read from.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Expr {
+        pub kind: ExprKind,
+        pub span: Span,
     }
 
-    #[test]
-    fn it_reads_add() {
-        let ts = tokens("1 kg.m/s2 + 5 kg.m/s2");
+    impl Expr {
+        fn binary(op: BinOp, lhs: Expr, rhs: Expr) -> Self {
+            let span = lhs.span.start..rhs.span.end;
+            Expr { kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), span }
+        }
 
-        assert_eq!(ts, vec![
-            quantity(1., "kg.m/s2"),
-            tokenizer::Token::Add,
-            quantity(5., "kg.m/s2")
-        ])
+        /// Moves every span `offset` bytes to the right, for expressions
+        /// read out of a longer statement (like a function's body).
+        pub fn offset(self, offset: usize) -> Self {
+            let kind = match self.kind {
+                ExprKind::Neg(operand) => ExprKind::Neg(Box::new(operand.offset(offset))),
+                ExprKind::Binary(op, lhs, rhs) => {
+                    ExprKind::Binary(op, Box::new(lhs.offset(offset)), Box::new(rhs.offset(offset)))
+                },
+                ExprKind::Call(name, args) => {
+                    ExprKind::Call(name, args.into_iter().map(|arg| arg.offset(offset)).collect())
+                },
+                ExprKind::Convert(operand, target) => ExprKind::Convert(Box::new(operand.offset(offset)), target),
+                kind => kind,
+            };
+            Expr { kind, span: self.span.start + offset..self.span.end + offset }
+        }
     }
 
-    #[test]
-    fn it_reads_sub() {
-        let ts = tokens("1 kg.m/s2 - 5 kg.m/s2");
+    const SEPARATOR_ERR: &str =
+        "Two quantities must be separated by one of the following tokens: '+', '-', '*', '/', '^', '->'";
 
-        assert_eq!(ts, vec![
-            quantity(1., "kg.m/s2"),
-            tokenizer::Token::Sub,
-            quantity(5., "kg.m/s2")
-        ])
+    /// Recursive descent, from the loosest binding operator to the
+    /// tightest:
+    ///
+    /// ```text
+    /// <expr>       ::= <conversion> | <expr> <comparison> <conversion>
+    ///                  | (<expr> | <term>) "~~" (<conversion> | <term>)
+    /// <comparison> ::= "==" | "!=" | "<" | "<=" | ">" | ">="
+    /// <conversion> ::= <sum> | <conversion> "->" <term>
+    /// <sum>        ::= <product> | <sum> ("+" | "-") <product>
+    /// <product>    ::= <unary> | <product> ("*" | "/") <unary>
+    /// <unary>      ::= "-" <unary> | <power>
+    /// <power>      ::= <primary> | <primary> "^" <unary>
+    /// <primary>    ::= <quantity> | <variable> | <call> | "(" <expr> ")"
+    /// <call>       ::= <variable> "(" (<expr> ("," <expr>)*)? ")"
+    /// ```
+    ///
+    /// So `^` is right-associative (`2^3^2` is `2^9`), and binds tighter
+    /// than unary minus (`-2^2` is `-4`). Comparisons bind loosest of all,
+    /// so `1 km -> m == 1000 m` converts before comparing. Names on either
+    /// side of `~~` are unit terms (`d ~~ h` is about days), so variables
+    /// there go in parentheses: `(d) ~~ h`.
+    #[derive(Debug)]
+    pub struct Parser {
+        tokens: Peekable<IntoIter<(Token, Span)>>,
+        // End of the last token we consumed
+        end: usize,
     }
 
-    #[test]
-    fn it_reads_mul() {
-        let ts = tokens("1 kg.m/s2 * 5 s2/kg/m");
+    impl Parser {
+        pub fn new(tokenizer: Tokenizer) -> Result<Self> {
+            Ok(Parser {
+                tokens: tokenizer.collect::<Result<Vec<_>>>()?.into_iter().peekable(),
+                end: 0,
+            })
+        }
 
-        assert_eq!(ts, vec![
-            quantity(1., "kg.m/s2"),
-            tokenizer::Token::Mul,
-            quantity(5., "s2/kg/m")
-        ])
-    }
+        fn next(&mut self) -> Option<(Token, Span)> {
+            let next = self.tokens.next();
+            if let Some((_, span)) = &next {
+                self.end = span.end;
+            }
+            next
+        }
 
-    #[test]
-    fn it_reads_div() {
-        let ts = tokens("1 kg.m/s2 / 5 kg.m/s2");
+        fn peek(&mut self) -> Option<&Token> {
+            self.tokens.peek().map(|(t, _)| t)
+        }
 
-        assert_eq!(ts, vec![
-            quantity(1., "kg.m/s2"),
-            tokenizer::Token::Div,
-            quantity(5., "kg.m/s2")
-        ])
-    }
+        pub fn parse(&mut self) -> Result<Expr> {
+            let expr = self.expr()?;
 
-    // -- Interpreter
+            match self.next() {
+                None => Ok(expr),
+                Some((Token::RParen, span)) => Err(CalculumError::syntax(
+                    "')' encountered without matching opening parenthesis.", span
+                )),
+                Some((_, span)) => Err(CalculumError::syntax(SEPARATOR_ERR, span)),
+            }
+        }
 
-    #[test]
-    #[should_panic]
-    fn it_requires_separator_between_quantities() {
-        interpret("1 kg.m/s 2 s/m");
-    }
+        fn expr(&mut self) -> Result<Expr> {
+            let mut expr = self.conversion()?;
 
-    #[test]
-    fn it_multiplies_quantities() {
-        assert_eq!(interpret("1 kg.m/s2 * 2 s/m"), Quantity::new(2., "kg/s"));
+            loop {
+                let op = match self.peek() {
+                    Some(Token::Eq) => BinOp::Eq,
+                    Some(Token::Ne) => BinOp::Ne,
+                    Some(Token::Lt) => BinOp::Lt,
+                    Some(Token::Le) => BinOp::Le,
+                    Some(Token::Gt) => BinOp::Gt,
+                    Some(Token::Ge) => BinOp::Ge,
+                    Some(Token::Commensurable) => BinOp::Commensurable,
+                    _ => break Ok(expr),
+                };
+                self.next();
+                expr = Expr::binary(op, expr, self.conversion()?);
+            }
+        }
+
+        fn conversion(&mut self) -> Result<Expr> {
+            let mut expr = self.sum()?;
+
+            while let Some(Token::Convert) = self.peek() {
+                self.next();
+                // The tokenizer always follows `->` with a unit term (or an error)
+                if let Some((Token::Term(target), span)) = self.next() {
+                    let span = expr.span.start..span.end;
+                    expr = Expr { kind: ExprKind::Convert(Box::new(expr), target), span };
+                }
+            }
+            Ok(expr)
+        }
+
+        fn sum(&mut self) -> Result<Expr> {
+            let mut expr = self.product()?;
+
+            loop {
+                let op = match self.peek() {
+                    Some(Token::Add) => BinOp::Add,
+                    Some(Token::Sub) => BinOp::Sub,
+                    _ => break Ok(expr),
+                };
+                self.next();
+                expr = Expr::binary(op, expr, self.product()?);
+            }
+        }
+
+        fn product(&mut self) -> Result<Expr> {
+            let mut expr = self.unary()?;
+
+            loop {
+                let op = match self.peek() {
+                    Some(Token::Mul) => BinOp::Mul,
+                    Some(Token::Div) => BinOp::Div,
+                    _ => break Ok(expr),
+                };
+                self.next();
+                expr = Expr::binary(op, expr, self.unary()?);
+            }
+        }
+
+        fn unary(&mut self) -> Result<Expr> {
+            if let Some(Token::Sub) = self.peek() {
+                let start = self.next().map_or(self.end, |(_, span)| span.start);
+                let operand = self.unary()?;
+                let span = start..operand.span.end;
+                return Ok(Expr { kind: ExprKind::Neg(Box::new(operand)), span })
+            }
+            self.power()
+        }
+
+        fn power(&mut self) -> Result<Expr> {
+            let base = self.primary()?;
+
+            match self.peek() {
+                Some(Token::Pow) => {
+                    self.next();
+                    Ok(Expr::binary(BinOp::Pow, base, self.unary()?))
+                },
+                _ => Ok(base),
+            }
+        }
+
+        fn primary(&mut self) -> Result<Expr> {
+            let err = "Expected a quantity, variable, or '('";
+
+            match self.next() {
+                Some((Token::Quantity(qn), span)) => Ok(Expr { kind: ExprKind::Quantity(qn), span }),
+                // Bare unit terms, after `~~` or starting with `[`, `{` or `%`
+                Some((Token::Term(term), span)) => Ok(Expr { kind: ExprKind::Term(term), span }),
+                Some((Token::Ident(name), span)) => match self.peek() {
+                    Some(Token::LParen) => self.call(name, span),
+                    _ => Ok(Expr { kind: ExprKind::Variable(name), span }),
+                },
+                Some((Token::LParen, span)) => {
+                    let expr = self.expr()?;
+                    match self.next() {
+                        Some((Token::RParen, end)) => Ok(Expr { kind: expr.kind, span: span.start..end.end }),
+                        Some((_, span)) => Err(CalculumError::syntax(SEPARATOR_ERR, span)),
+                        None => Err(CalculumError::syntax(
+                            "'(' encountered without matching closing parenthesis.", span
+                        )),
+                    }
+                },
+                Some((t, span)) => Err(CalculumError::syntax(format!("{} but found {:?}.", err, t), span)),
+                None => Err(CalculumError::syntax(
+                    format!("{} but the expression ended.", err), self.end..self.end
+                )),
+            }
+        }
+
+        /// The arguments of a call, starting at the `(`.
+        fn call(&mut self, name: String, name_span: Span) -> Result<Expr> {
+            let (_, paren_span) = self.next().expect("A call starts with '('");
+            let mut args = vec![];
+
+            if let Some(Token::RParen) = self.peek() {
+                self.next();
+            } else {
+                loop {
+                    args.push(self.expr()?);
+                    match self.next() {
+                        Some((Token::Comma, _)) => continue,
+                        Some((Token::RParen, _)) => break,
+                        Some((_, span)) => return Err(CalculumError::syntax(
+                            "Expected ',' or ')' after a function argument.", span
+                        )),
+                        None => return Err(CalculumError::syntax(
+                            "'(' encountered without matching closing parenthesis.", paren_span
+                        )),
+                    }
+                }
+            }
+
+            Ok(Expr { kind: ExprKind::Call(name, args), span: name_span.start..self.end })
+        }
     }
+}
 
-    #[test]
-    fn it_divides_quantities() {
-        assert_eq!(interpret("1 kg.m/s2 / 2 m/s"), Quantity::new(0.5, "kg/s"));
+mod interpreter {
+    use std::ops::RangeInclusive;
+
+    use calculum_core::rational::Rational;
+
+    use super::{arbitrary_unit_error, tokenizer, Environment, Function, Param, Quantity, Value};
+    use super::parser::{BinOp, Expr, ExprKind, Parser};
+    use super::tokenizer::Token;
+    use crate::ucum::error::{CalculumError, Result, Span};
+    use crate::ucum::registry::UnitRegistry;
+    use crate::ucum::term::UnitTerm;
+
+    /// Special units can only be scaled by plain numbers (`37 Cel * 2`,
+    /// `2 * 37 Cel` or `37 Cel / 2`), and can't be divided by.
+    fn check_not_special(op: BinOp, lhs: &Quantity, rhs: &Quantity, span: Span) -> Result<()> {
+        let special = if rhs.term.special().is_some() && (op == BinOp::Div || !lhs.term.is_unity()) {
+            Some(&rhs.term)
+        } else if lhs.term.special().is_some() && !rhs.term.is_unity() {
+            Some(&lhs.term)
+        } else {
+            None
+        };
+
+        match special {
+            Some(term) => Err(CalculumError::SpecialUnit { unit: term.to_string(), span }),
+            None => Ok(()),
+        }
     }
 
-    #[test]
-    fn it_adds_quantities_with_the_same_units() {
-        assert_eq!(interpret("1 kg.m/s2 + 5 N"), Quantity::new(6., "N"))
+    fn check_commensurable(lhs: &Quantity, rhs: &Quantity, span: Span) -> Result<()> {
+        if lhs.term.is_commensurable(&rhs.term) {
+            Ok(())
+        } else if let Some(err) = arbitrary_unit_error(&lhs.term, &rhs.term, span.clone()) {
+            Err(err)
+        } else {
+            Err(CalculumError::Incommensurable {
+                lhs: lhs.term.dimension_name(),
+                rhs: rhs.term.dimension_name(),
+                span
+            })
+        }
     }
 
-    #[test]
-    fn it_subs_quantities_with_the_same_units() {
-        assert_eq!(interpret("10 kg.m/s2 - 5 N"), Quantity::new(5., "N"))
+    /// The magnitude of a dimensionless quantity (`50 %` is `0.5`).
+    fn dimensionless(qn: &Quantity, env: &Environment, span: Span) -> Result<f64> {
+        Ok(qn.convert_to(&UnitTerm::new("1", &env.registry)?).map_err(|e| e.at(span))?.mag)
     }
 
-    #[test]
-    #[should_panic]
-    fn it_doesnt_add_quantities_with_different_units() {
-        println!("{}", interpret("10 kg.m/s + 5 N"));
+    /// Plain numbers take any power. Everything else needs a rational
+    /// exponent that leaves its units with integer exponents (`(3 m)^2`,
+    /// `(8 m3)^(1/3)`), unless rational exponents are enabled.
+    ///
+    /// Negative numbers only take integer powers (`(-8)^(1/3)` has no real
+    /// answer), and results too large for a float are an error rather than
+    /// `inf`.
+    fn pow(base: &Quantity, exp: f64, function: &str, env: &Environment, span: Span) -> Result<Quantity> {
+        if base.mag < 0. && exp.fract() != 0. {
+            return Err(CalculumError::OutOfDomain {
+                function: function.to_string(),
+                value: base.mag.to_string(),
+                span
+            })
+        }
+        let qn = raise(base, exp, env, span.clone())?;
+
+        match qn.mag {
+            mag if mag.is_finite() => Ok(qn),
+            _ if base.mag == 0. => Err(CalculumError::DivisionByZero { span }),
+            _ => Err(CalculumError::Overflow { span }),
+        }
+    }
+
+    fn raise(base: &Quantity, exp: f64, env: &Environment, span: Span) -> Result<Quantity> {
+        if base.term.is_unity() {
+            return Ok(Quantity { mag: base.mag.powf(exp), term: base.term.clone() })
+        }
+
+        let fractional_err = || CalculumError::FractionalExponent {
+            term: base.term.to_string(),
+            exponent: exp.to_string(),
+            span: span.clone()
+        };
+        let rational = Rational::approximate(exp, i8::MAX as i32).ok_or_else(fractional_err)?;
+        let qn = base.pow(rational).map_err(|e| e.at(span.clone()))?;
+
+        if qn.term.has_fractional_exponents() && !env.rational_exponents {
+            return Err(fractional_err())
+        }
+        Ok(qn)
+    }
+
+    /// Raises a syntax error unless the call has between `arity.start()`
+    /// and `arity.end()` arguments.
+    pub(super) fn check_arity(name: &str, args: &[Expr], arity: RangeInclusive<usize>, span: &Span) -> Result<()> {
+        if arity.contains(&args.len()) {
+            return Ok(())
+        }
+
+        let expected = match (*arity.start(), *arity.end()) {
+            (min, max) if min == max => min.to_string(),
+            (min, usize::MAX) => format!("at least {}", min),
+            (min, max) => format!("{} to {}", min, max),
+        };
+        Err(CalculumError::syntax(
+            format!("'{}' takes {} argument(s), but got {}.", name, expected, args.len()), span.clone()
+        ))
+    }
+
+    /// A unit term argument, as in `ROUND(1 [mi_i], 2, km/h)`. Names in it
+    /// are unit atoms rather than variables.
+    pub(super) fn unit_arg(expr: &Expr, env: &Environment) -> Result<UnitTerm> {
+        match &expr.kind {
+            ExprKind::Term(term) => Ok(term.clone()),
+            ExprKind::Variable(name) => {
+                UnitTerm::new(name, &env.registry).map_err(|e| e.offset(expr.span.start))
+            },
+            ExprKind::Binary(BinOp::Mul, lhs, rhs) => {
+                unit_arg(lhs, env)?.checked_mul(&unit_arg(rhs, env)?).map_err(|e| e.at(expr.span.clone()))
+            },
+            ExprKind::Binary(BinOp::Div, lhs, rhs) => {
+                unit_arg(lhs, env)?.checked_div(&unit_arg(rhs, env)?).map_err(|e| e.at(expr.span.clone()))
+            },
+            _ => Err(CalculumError::syntax("Expected a unit term, e.g., 'km/h'.", expr.span.clone())),
+        }
+    }
+
+    /// The arguments, converted to the units of the first (so `SUM(1 km,
+    /// 1 m)` is in `km`). They all have to be commensurable.
+    fn commensurate(args: &[Expr], env: &Environment) -> Result<Vec<Quantity>> {
+        let mut qns: Vec<Quantity> = vec![];
+
+        for arg in args {
+            let mut qn = quantity(arg, env)?;
+            if let Some(first) = qns.first() {
+                check_commensurable(first, &qn, arg.span.clone())?;
+                qn = qn.convert_to(&first.term).map_err(|e| e.at(arg.span.clone()))?;
+            }
+            qns.push(qn);
+        }
+        Ok(qns)
+    }
+
+    /// An angle (`30 deg`, `100 gon`) in radians, or a dimensionless
+    /// quantity as it is.
+    fn radians(qn: &Quantity, env: &Environment, span: Span) -> Result<f64> {
+        let rad = UnitTerm::new("rad", &env.registry)?;
+
+        if qn.term.is_commensurable(&rad) {
+            Ok(qn.convert_to(&rad).map_err(|e| e.at(span))?.mag)
+        } else {
+            dimensionless(qn, env, span)
+        }
+    }
+
+    fn number(x: f64, env: &Environment) -> Result<Value> {
+        Quantity::new(x, "", &env.registry).map(Value::from)
+    }
+
+    /// The step for `FLOOR` & `CEILING`, in the units of `qn` (`1` of them
+    /// by default).
+    fn significance(qn: &Quantity, arg: Option<&Expr>, env: &Environment) -> Result<f64> {
+        let Some(arg) = arg else {
+            return Ok(1.)
+        };
+
+        let step = quantity(arg, env)?;
+        check_commensurable(qn, &step, arg.span.clone())?;
+        match step.convert_to(&qn.term).map_err(|e| e.at(arg.span.clone()))?.mag {
+            0. => Err(CalculumError::DivisionByZero { span: arg.span.clone() }),
+            step => Ok(step),
+        }
+    }
+
+    /// `qn` rounded to a multiple of `step` with `round` (e.g., `f64::floor`).
+    /// Ratios within floating point error of an integer count as that
+    /// integer, and the multiple is rounded to as many decimals as `step`
+    /// has, so `FLOOR(0.3, 0.1)` is `0.3` (rather than `3 * 0.1`, which is
+    /// `0.30000000000000004`).
+    fn round_to(qn: Quantity, step: f64, round: fn(f64) -> f64) -> Quantity {
+        let ratio = qn.mag / step;
+        let ratio = if (ratio - ratio.round()).abs() < 1e-9 { ratio.round() } else { round(ratio) };
+        let mag = match step.to_string().split_once('.') {
+            Some((_, decimals)) if decimals.len() <= 15 => {
+                let scale = 10f64.powi(decimals.len() as i32);
+                (ratio * step * scale).round() / scale
+            },
+            _ => ratio * step,
+        };
+        Quantity { mag, term: qn.term }
+    }
+
+    /// The names of the built-in functions, which can't be redefined.
+    const BUILTINS: [&str; 16] = [
+        "SQRT", "SUM", "AVERAGE", "MIN", "MAX", "ABS", "ROUND", "FLOOR", "CEILING", "IF",
+        "SIN", "COS", "TAN", "EXP", "LN", "LOG10",
+    ];
+
+    fn is_builtin(name: &str) -> bool {
+        BUILTINS.contains(&name.to_uppercase().as_str())
+    }
+
+    /// Calls a user-defined function. Its body sees the caller's variables,
+    /// plus the parameters, but not the function itself (so it can't
+    /// recurse, directly or otherwise). Errors in the body are reported at
+    /// the call.
+    fn call_user(name: &str, function: &Function, args: &[Expr], env: &Environment, span: Span) -> Result<Value> {
+        check_arity(name, args, function.params.len()..=function.params.len(), &span)?;
+
+        let mut scope = env.clone();
+        scope.functions.remove(name);
+        for (param, arg) in function.params.iter().zip(args) {
+            let value = match &param.unit {
+                Some(unit) => quantity(arg, env)?.convert_to(unit).map_err(|e| e.at(arg.span.clone()))?.into(),
+                None => eval(arg, env)?,
+            };
+            scope.set(&param.name, value);
+        }
+
+        let result = eval(&function.body, &scope).map_err(|e| e.at(span.clone()))?;
+        match &function.unit {
+            Some(unit) => Ok(expect_quantity(result, &span)?.convert_to(unit).map_err(|e| e.at(span))?.into()),
+            None => Ok(result),
+        }
+    }
+
+    /// The built-in functions. Names are case-insensitive, as in Excel.
+    ///
+    /// - `SUM`, `MIN`, `MAX` & `AVERAGE` take commensurable quantities, and
+    ///   answer in the units of the first.
+    /// - `ROUND(x, n, unit)` rounds to `n` decimals (`0` by default), after
+    ///   converting to `unit` (if given).
+    /// - `FLOOR(x, step)` & `CEILING(x, step)` round to a multiple of `step`
+    ///   (`1` of `x`'s units by default).
+    /// - `IF(condition, then, else)` only evaluates the branch it takes, and
+    ///   is `false` when the condition is false & there's no `else`.
+    /// - `sin`, `cos` & `tan` take angles (in any unit, e.g., `deg` or `'`)
+    ///   or dimensionless quantities (in radians); `exp`, `ln` & `log10`
+    ///   only take dimensionless quantities (`exp(2 s / 1 s)`).
+    fn call(name: &str, args: &[Expr], env: &Environment, span: Span) -> Result<Value> {
+        if let Some(function) = env.function(name) {
+            return call_user(name, function, args, env, span)
+        }
+        let arity = |arity: RangeInclusive<usize>| check_arity(name, args, arity, &span);
+
+        match name.to_uppercase().as_str() {
+            "SQRT" => {
+                arity(1..=1)?;
+                pow(&quantity(&args[0], env)?, 0.5, name, env, span).map(Value::from)
+            },
+            "SUM" => {
+                arity(1..=usize::MAX)?;
+                // As with `+`, so there's no sum of temperatures in `Cel`
+                let mut sum = quantity(&args[0], env)?;
+                for arg in &args[1..] {
+                    let qn = quantity(arg, env)?;
+                    check_commensurable(&sum, &qn, arg.span.clone())?;
+                    sum = sum.checked_add(&qn).map_err(|e| e.at(arg.span.clone()))?;
+                }
+                Ok(sum.into())
+            },
+            upper @ ("AVERAGE" | "MIN" | "MAX") => {
+                arity(1..=usize::MAX)?;
+                let qns = commensurate(args, env)?;
+                let mags = qns.iter().map(|qn| qn.mag);
+                let mag = match upper {
+                    "AVERAGE" => mags.sum::<f64>() / qns.len() as f64,
+                    "MIN" => mags.fold(f64::INFINITY, f64::min),
+                    _ => mags.fold(f64::NEG_INFINITY, f64::max),
+                };
+                Ok(Quantity { mag, term: qns[0].term.clone() }.into())
+            },
+            "ABS" => {
+                arity(1..=1)?;
+                let qn = quantity(&args[0], env)?;
+                Ok(Quantity { mag: qn.mag.abs(), term: qn.term }.into())
+            },
+            "ROUND" => {
+                arity(1..=3)?;
+                let mut qn = quantity(&args[0], env)?;
+                if let Some(unit) = args.get(2) {
+                    let target = unit_arg(unit, env)?;
+                    qn = qn.convert_to(&target).map_err(|e| e.at(span.clone()))?;
+                }
+                let digits = match args.get(1) {
+                    Some(arg) => dimensionless(&quantity(arg, env)?, env, arg.span.clone())?.trunc() as i32,
+                    None => 0,
+                };
+                Ok(round_to(qn, 10f64.powi(-digits), f64::round).into())
+            },
+            upper @ ("FLOOR" | "CEILING") => {
+                arity(1..=2)?;
+                let qn = quantity(&args[0], env)?;
+                let step = significance(&qn, args.get(1), env)?;
+                let round = if upper == "FLOOR" { f64::floor } else { f64::ceil };
+                Ok(round_to(qn, step, round).into())
+            },
+            upper @ ("SIN" | "COS" | "TAN") => {
+                arity(1..=1)?;
+                let x = radians(&quantity(&args[0], env)?, env, args[0].span.clone())?;
+                number(match upper {
+                    "SIN" => x.sin(),
+                    "COS" => x.cos(),
+                    _ => x.tan(),
+                }, env)
+            },
+            upper @ ("EXP" | "LN" | "LOG10") => {
+                arity(1..=1)?;
+                let x = dimensionless(&quantity(&args[0], env)?, env, args[0].span.clone())?;
+                if upper != "EXP" && x <= 0. {
+                    return Err(CalculumError::OutOfDomain {
+                        function: name.to_string(),
+                        value: x.to_string(),
+                        span: args[0].span.clone()
+                    })
+                }
+                number(match upper {
+                    "EXP" => x.exp(),
+                    "LN" => x.ln(),
+                    _ => x.log10(),
+                }, env)
+            },
+            "IF" => {
+                arity(2..=3)?;
+                let condition = match eval(&args[0], env)? {
+                    Value::Bool(b) => b,
+                    value => return Err(CalculumError::TypeMismatch {
+                        expected: "boolean".to_string(),
+                        found: value.type_name().to_string(),
+                        span: args[0].span.clone()
+                    }),
+                };
+                match (condition, args.get(2)) {
+                    (true, _) => eval(&args[1], env),
+                    (false, Some(otherwise)) => eval(otherwise, env),
+                    (false, None) => Ok(Value::Bool(false)),
+                }
+            },
+            _ => Err(CalculumError::UnknownFunction { name: name.to_string(), span }),
+        }
+    }
+
+    fn expect_quantity(value: Value, span: &Span) -> Result<Quantity> {
+        match value {
+            Value::Quantity(qn) => Ok(qn),
+            value => Err(CalculumError::TypeMismatch {
+                expected: "quantity".to_string(),
+                found: value.type_name().to_string(),
+                span: span.clone()
+            }),
+        }
+    }
+
+    /// Evaluates an expression that has to be a quantity (e.g., an operand
+    /// of `+`).
+    fn quantity(expr: &Expr, env: &Environment) -> Result<Quantity> {
+        expect_quantity(eval(expr, env)?, &expr.span)
+    }
+
+    /// Booleans are (un)equal to booleans. Quantities have to be
+    /// commensurable to compare, and `rhs` is converted to the units of
+    /// `lhs` first, so `1 [ft_i] == 12 [in_i]` (up to floating point error).
+    /// `~~` is the exception: it's `false` for incommensurable quantities.
+    fn compare(op: BinOp, lhs_expr: &Expr, rhs_expr: &Expr, env: &Environment, span: Span) -> Result<bool> {
+        let (lhs, rhs) = (eval(lhs_expr, env)?, eval(rhs_expr, env)?);
+
+        if let (Value::Bool(lhs), Value::Bool(rhs), BinOp::Eq | BinOp::Ne) = (&lhs, &rhs, op) {
+            return Ok((lhs == rhs) == (op == BinOp::Eq))
+        }
+
+        let lhs = expect_quantity(lhs, &lhs_expr.span)?;
+        let rhs = expect_quantity(rhs, &rhs_expr.span)?;
+
+        if op == BinOp::Commensurable {
+            return Ok(lhs.term.is_commensurable(&rhs.term))
+        }
+
+        check_commensurable(&lhs, &rhs, span.clone())?;
+        let (lhs, rhs) = (lhs.mag, rhs.convert_to(&lhs.term).map_err(|e| e.at(span))?.mag);
+        let equal = (lhs - rhs).abs() <= 1e-12 * lhs.abs().max(rhs.abs());
+
+        Ok(match op {
+            BinOp::Eq => equal,
+            BinOp::Ne => !equal,
+            BinOp::Lt => lhs < rhs && !equal,
+            BinOp::Le => lhs < rhs || equal,
+            BinOp::Gt => lhs > rhs && !equal,
+            BinOp::Ge => lhs > rhs || equal,
+            _ => unreachable!("{:?} isn't a comparison", op),
+        })
+    }
+
+    pub fn eval(expr: &Expr, env: &Environment) -> Result<Value> {
+        let span = expr.span.clone();
+
+        match &expr.kind {
+            ExprKind::Quantity(qn) => Ok(qn.clone().into()),
+            ExprKind::Term(term) => Ok(Quantity { mag: 1., term: term.clone() }.into()),
+            ExprKind::Variable(name) => env.get(name).cloned().ok_or_else(|| {
+                CalculumError::UnknownVariable { name: name.clone(), span }
+            }),
+            ExprKind::Neg(operand) => {
+                let qn = quantity(operand, env)?;
+                Ok(Quantity { mag: -qn.mag, term: qn.term }.into())
+            },
+            ExprKind::Call(name, args) => call(name, args, env, span),
+            ExprKind::Convert(operand, target) => {
+                quantity(operand, env)?.convert_to(target).map(Value::from).map_err(|e| e.at(span))
+            },
+            ExprKind::Binary(op, lhs_expr, rhs_expr) if op.is_comparison() => {
+                compare(*op, lhs_expr, rhs_expr, env, span).map(Value::from)
+            },
+            ExprKind::Binary(op, lhs_expr, rhs_expr) => {
+                let mut lhs = quantity(lhs_expr, env)?;
+                let rhs = quantity(rhs_expr, env)?;
+
+                match op {
+                    BinOp::Add => {
+                        check_commensurable(&lhs, &rhs, span.clone())?;
+                        lhs = lhs.checked_add(&rhs).map_err(|e| e.at(span))?
+                    },
+                    BinOp::Sub => {
+                        check_commensurable(&lhs, &rhs, span.clone())?;
+                        lhs = lhs.checked_sub(&rhs).map_err(|e| e.at(span))?
+                    },
+                    BinOp::Mul => {
+                        check_not_special(*op, &lhs, &rhs, span.clone())?;
+                        lhs = lhs.checked_mul(&rhs).map_err(|e| e.at(span))?
+                    },
+                    BinOp::Div => {
+                        check_not_special(*op, &lhs, &rhs, span.clone())?;
+                        if rhs.mag == 0. {
+                            return Err(CalculumError::DivisionByZero { span: rhs_expr.span.clone() })
+                        }
+                        lhs = lhs.checked_div(&rhs).map_err(|e| e.at(span))?
+                    },
+                    BinOp::Pow => {
+                        let exp = dimensionless(&rhs, env, rhs_expr.span.clone())?;
+                        return pow(&lhs, exp, "^", env, span).map(Value::from)
+                    },
+                    _ => unreachable!("{:?} is a comparison", op),
+                }
+                Ok(lhs.into())
+            },
+        }
+    }
+
+    pub fn parse(s: &str, registry: &UnitRegistry) -> Result<Expr> {
+        Parser::new(tokenizer::Tokenizer::new(s, registry))?.parse()
+    }
+
+    pub fn interpret(s: &str, env: &Environment) -> Result<Value> {
+        eval(&parse(s, &env.registry)?, env)
+    }
+
+    /// The word starting at `pos` (after any whitespace), up to whitespace or
+    /// `=`.
+    fn next_word(s: &str, pos: usize) -> (&str, Span) {
+        let start = pos + s[pos..].len() - s[pos..].trim_start().len();
+        let len = s[start..].find(|c: char| c.is_ascii_whitespace() || c == '=').unwrap_or(s.len() - start);
+        (&s[start..start + len], start..start + len)
+    }
+
+    /// The rest of the statement after `keyword`, if it starts with it.
+    fn after_keyword(s: &str, keyword: &str) -> Option<usize> {
+        let start = s.len() - s.trim_start().len();
+        let rest = s[start..].strip_prefix(keyword)?;
+        rest.starts_with(|c: char| c.is_ascii_whitespace()).then_some(start + keyword.len())
+    }
+
+    /// `define [pallet] = 40 [ft_i]3`: the new atom is worth the quantity.
+    /// Atoms in square brackets are non-metric (as in UCUM), and the rest
+    /// are metric, so they take prefixes.
+    fn define(s: &str, pos: usize, registry: &mut UnitRegistry) -> Result<()> {
+        let (atom, atom_span) = next_word(s, pos);
+        if atom.is_empty() {
+            return Err(CalculumError::syntax("Expected a unit atom to define.", atom_span))
+        }
+
+        let rest = &s[atom_span.end..];
+        let eq = atom_span.end + rest.len() - rest.trim_start().len();
+        if !s[eq..].starts_with('=') {
+            return Err(CalculumError::syntax("Expected '=' after the unit atom.", eq..eq))
+        }
+
+        let rhs_span = eq + 1..s.len();
+        let err = "A definition must be a single quantity, e.g., '40 [ft_i]3'.";
+        let (factor, term) = {
+            let mut ts = tokenizer::Tokenizer::new(&s[rhs_span.clone()], registry);
+            match (ts.next().transpose(), ts.next()) {
+                (Ok(Some((Token::Quantity(qn), _))), None) => (qn.mag, qn.term.to_string()),
+                (Err(e), _) => return Err(e.offset(rhs_span.start)),
+                _ => return Err(CalculumError::syntax(err, rhs_span)),
+            }
+        };
+
+        registry.define_unit(atom, factor, &term, !atom.starts_with('['))
+            .map_err(|e| match e {
+                CalculumError::SpecialUnitDefinition { .. } => e.at(rhs_span),
+                _ => e.at(atom_span),
+            })
+    }
+
+    /// `dimension [currency]`: a new base unit, for a dimension of its own.
+    fn dimension(s: &str, pos: usize, registry: &mut UnitRegistry) -> Result<()> {
+        let (atom, atom_span) = next_word(s, pos);
+        if atom.is_empty() {
+            return Err(CalculumError::syntax("Expected a unit atom for the dimension.", atom_span))
+        }
+        if !s[atom_span.end..].trim().is_empty() {
+            return Err(CalculumError::syntax(
+                "Expected nothing after the unit atom.", atom_span.end..s.len()
+            ))
+        }
+
+        registry.define_base_unit(atom).map_err(|e| e.at(atom_span))
+    }
+
+    /// Where the parts of a function definition are, e.g., in
+    /// `speed(d: m, t: s): m/s = d / t`: the name, the parameters (inside
+    /// the parentheses), the unit after the `:` (if any), and where the
+    /// body starts.
+    pub(super) struct Signature<'a> {
+        pub(super) name: &'a str,
+        name_span: Span,
+        params: Span,
+        unit: Option<Span>,
+        body: usize,
+    }
+
+    /// A name, parentheses & `=` (or `:` & `=`) make a function definition.
+    /// (`f(2 m) == 4 m` is still a comparison.)
+    fn function_definition(s: &str) -> Option<Signature<'_>> {
+        let start = s.len() - s.trim_start().len();
+        if !s[start..].starts_with(tokenizer::starts_ident) {
+            return None
+        }
+
+        let name_end = s[start..].find(|c| !tokenizer::is_ident_char(c)).map_or(s.len(), |len| start + len);
+        let open = name_end + s[name_end..].len() - s[name_end..].trim_start().len();
+        if !s[open..].starts_with('(') {
+            return None
+        }
+
+        let mut depth = 0;
+        let close = open + s[open..].find(|c| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {},
+            }
+            depth == 0
+        })?;
+
+        let rest = &s[close + 1..];
+        let after = close + 1 + rest.len() - rest.trim_start().len();
+        let (unit, eq) = match s[after..].chars().next()? {
+            ':' => {
+                let eq = after + s[after..].find('=')?;
+                (Some(after + 1..eq), eq)
+            },
+            '=' => (None, after),
+            _ => return None,
+        };
+
+        (!s[eq..].starts_with("==")).then(|| Signature {
+            name: &s[start..name_end],
+            name_span: start..name_end,
+            params: open + 1..close,
+            unit,
+            body: eq + 1,
+        })
+    }
+
+    /// The unit term in `span` of `s`, e.g., after a parameter's `:`.
+    fn declared_unit(s: &str, span: Span, registry: &UnitRegistry) -> Result<UnitTerm> {
+        let text = s[span.clone()].trim();
+        let start = span.start + s[span.clone()].len() - s[span.clone()].trim_start().len();
+
+        if text.is_empty() {
+            return Err(CalculumError::syntax("Expected a unit term after ':'.", span))
+        }
+        UnitTerm::new(text, registry).map_err(|e| e.offset(start))
+    }
+
+    /// The parameters in `span` of `s`: `d: m, t: s`, or just `x, y`.
+    fn params(s: &str, span: Span, registry: &UnitRegistry) -> Result<Vec<Param>> {
+        let list = &s[span.clone()];
+        if list.trim().is_empty() {
+            return Ok(vec![])
+        }
+
+        // Split at the commas outside of parentheses (as in `kg/(m.s2)`)
+        let mut depth = 0;
+        let mut segments = vec![];
+        let mut segment_start = span.start;
+        for (i, c) in list.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    segments.push(segment_start..span.start + i);
+                    segment_start = span.start + i + 1;
+                },
+                _ => {},
+            }
+        }
+        segments.push(segment_start..span.end);
+
+        let mut params: Vec<Param> = vec![];
+        for segment in segments {
+            let (name_end, unit) = match s[segment.clone()].find(':') {
+                Some(i) => (segment.start + i, Some(segment.start + i + 1..segment.end)),
+                None => (segment.end, None),
+            };
+            let (name, name_span) = next_word(&s[..name_end], segment.start);
+
+            let is_name = name.starts_with(tokenizer::starts_ident) && name.chars().all(tokenizer::is_ident_char);
+            if !is_name || !s[name_span.end..name_end].trim().is_empty() {
+                return Err(CalculumError::syntax("Expected a parameter name.", segment))
+            }
+            if params.iter().any(|param| param.name == name) {
+                return Err(CalculumError::syntax(format!("The parameter '{}' is repeated.", name), name_span))
+            }
+
+            params.push(Param {
+                name: name.to_string(),
+                unit: unit.map(|span| declared_unit(s, span, registry)).transpose()?,
+            });
+        }
+        Ok(params)
+    }
+
+    /// `speed(d: m, t: s): m/s = d / t`. The body is parsed now (so syntax
+    /// errors show up right away), but evaluated at each call. Functions
+    /// can be redefined, but the built-ins can't.
+    fn define_function(s: &str, signature: Signature, env: &mut Environment) -> Result<()> {
+        if is_builtin(signature.name) {
+            return Err(CalculumError::AlreadyDefined {
+                name: signature.name.to_string(),
+                span: signature.name_span
+            })
+        }
+
+        let function = Function {
+            params: params(s, signature.params, &env.registry)?,
+            unit: signature.unit.map(|span| declared_unit(s, span, &env.registry)).transpose()?,
+            body: parse(&s[signature.body..], &env.registry)
+                .map(|body| body.offset(signature.body))
+                .map_err(|e| e.offset(signature.body))?,
+        };
+        env.define_function(signature.name, function);
+        Ok(())
+    }
+
+    /// `accel = 10 m/s2`: the variable's name, and where the expression
+    /// starts. (`=` is only an assignment after a lone name.)
+    fn assignment(s: &str) -> Option<(&str, usize)> {
+        let start = s.len() - s.trim_start().len();
+        if !s[start..].starts_with(tokenizer::starts_ident) {
+            return None
+        }
+
+        let len = s[start..].find(|c| !tokenizer::is_ident_char(c)).unwrap_or(s.len() - start);
+        let rest = &s[start + len..];
+        let eq = start + len + rest.len() - rest.trim_start().len();
+
+        (s[eq..].starts_with('=') && !s[eq..].starts_with("=="))
+            .then_some((&s[start..start + len], eq + 1))
+    }
+
+    /// The kinds of statement, with where their parts are.
+    pub(super) enum Statement<'a> {
+        /// `define [pallet] = 40 [ft_i]3`, from after the keyword
+        Define(usize),
+        /// `dimension [currency]`, from after the keyword
+        Dimension(usize),
+        Function(Signature<'a>),
+        /// `accel = 10 m/s2`: the name, and where the expression starts
+        Assignment(&'a str, usize),
+        Expression,
+    }
+
+    pub(super) fn statement(s: &str) -> Statement<'_> {
+        if let Some(pos) = after_keyword(s, "define") {
+            Statement::Define(pos)
+        } else if let Some(pos) = after_keyword(s, "dimension") {
+            Statement::Dimension(pos)
+        } else if let Some(signature) = function_definition(s) {
+            Statement::Function(signature)
+        } else if let Some((name, pos)) = assignment(s) {
+            Statement::Assignment(name, pos)
+        } else {
+            Statement::Expression
+        }
+    }
+
+    /// Runs a statement: a definition (which extends the units for the
+    /// statements that follow), a function definition, an assignment, or an
+    /// expression, which evaluates to a value.
+    pub fn execute(s: &str, env: &mut Environment) -> Result<Option<Value>> {
+        match statement(s) {
+            Statement::Define(pos) => define(s, pos, &mut env.registry).map(|_| None),
+            Statement::Dimension(pos) => dimension(s, pos, &mut env.registry).map(|_| None),
+            Statement::Function(signature) => define_function(s, signature, env).map(|_| None),
+            Statement::Assignment(name, pos) => {
+                let value = interpret(&s[pos..], env).map_err(|e| e.offset(pos))?;
+                env.set(name, value);
+                Ok(None)
+            },
+            Statement::Expression => interpret(s, env).map(Some),
+        }
+    }
+}
+
+pub use interpreter::{eval, execute, interpret, parse};
+
+/// # Checking
+/// Infers the type of every (sub-)expression in a script without
+/// evaluating any of it: a quantity of some dimension, or a boolean. So
+/// the unit errors in a script (`1 m + 1 s`, `2 kg -> m`, `sin(2 m)`,
+/// `sqrt(2 m)`, `1 Cel * 2 Cel`, `1 [iU] + 1 g`, a function called with
+/// the wrong units, ...) show up at once, with their spans, before it runs.
+///
+/// The checker only reports what would go wrong at runtime, whatever the
+/// values. Where it can't tell (an untyped parameter, a power with a
+/// variable exponent, an `IF` with branches of different dimensions), it
+/// gives up on the type rather than guess. So errors that depend on the
+/// values (`1 / 0 m`, `ln(0)`, `(-8)^(1/3)`) or on how the units are
+/// written rather than their dimension (`sqrt(1 ha)`, `37 Cel * 2 %`) are
+/// left to the interpreter. As at runtime, a function's body can use the
+/// variables & functions defined after it, so the names it doesn't know
+/// yet are checked at each call instead.
+mod checker {
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::ops::RangeInclusive;
+
+    use calculum_core::constants::DimensionVector;
+    use calculum_core::rational::Rational;
+
+    use super::{Environment, Function, Value};
+    use super::interpreter::{check_arity, eval, execute, parse, statement, unit_arg, Statement};
+    use super::parser::{BinOp, Expr, ExprKind};
+    use crate::ucum::error::{CalculumError, Span};
+    use crate::ucum::term::{ReducedForm, ReducibleUnit, UnitTerm};
+
+    /// The dimension of a quantity, as the base units it reduces to. Unlike
+    /// a `DimensionVector`, that covers arbitrary units (`[iU]`) & the base
+    /// units defined at runtime (`[currency]`). Quantities in a special
+    /// unit (`Cel`) have its ratio unit's dimension, & remember the special
+    /// unit, which doesn't multiply or add.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Dimension {
+        base: ReducedForm,
+        special: Option<String>,
+    }
+
+    impl Dimension {
+        pub fn of(term: &UnitTerm) -> Self {
+            let units = term.dimensional_base_units().units;
+            Dimension {
+                base: ReducedForm { mag: 1., units },
+                special: term.special().map(|_| term.to_string()),
+            }
+        }
+
+        fn dimensionless() -> Self {
+            Dimension { base: ReducedForm { mag: 1., units: vec![] }, special: None }
+        }
+
+        /// The exponents of the base dimensions, if it's made of UCUM's base
+        /// units.
+        pub fn vector(&self) -> Option<DimensionVector> {
+            self.base.dimension()
+        }
+
+        fn is_dimensionless(&self) -> bool {
+            self.base.units.is_empty()
+        }
+
+        /// A plain number, which scales any quantity.
+        fn is_plain(&self) -> bool {
+            self.is_dimensionless() && self.special.is_none()
+        }
+
+        fn is_arbitrary(&self) -> bool {
+            self.base.units.iter().any(|u| u.is_arbitrary())
+        }
+
+        fn has_fractional_exponents(&self) -> bool {
+            self.base.units.iter().any(|u| !u.exp.is_integer())
+        }
+
+        /// Fails (as at runtime) if an exponent overflows.
+        fn pow(&self, exp: Rational) -> Result<Self, CalculumError> {
+            let overflow = || CalculumError::ExponentOverflow { exponent: exp.to_string(), span: 0..0 };
+            let units = self.base.units.iter()
+                .map(|u| u.exp.checked_mul(exp).map(|e| crate::ucum::unit::Unit { exp: e, ..u.clone() }).ok_or_else(overflow))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Dimension { base: ReducedForm { mag: 1., units: UnitTerm::normalize_units(units)? }, special: None })
+        }
+
+        /// As at runtime, a plain number keeps the other side's special
+        /// unit (`2 * 37 Cel`).
+        fn product(self, other: Dimension) -> Result<Self, CalculumError> {
+            let special = match (self.is_plain(), other.is_plain()) {
+                (true, _) => other.special,
+                (_, true) => self.special,
+                _ => None,
+            };
+            let mut units = self.base.units;
+            units.extend(other.base.units);
+            Ok(Dimension { base: ReducedForm { mag: 1., units: UnitTerm::normalize_units(units)? }, special })
+        }
+
+        fn quotient(self, other: Dimension) -> Result<Self, CalculumError> {
+            self.product(other.pow(-Rational::ONE)?)
+        }
+    }
+
+    impl fmt::Display for Dimension {
+        /// `L.T-1`, or the base units for those without a `DimensionVector`
+        /// (`[currency].L-1` reads worse than `[currency]/m`).
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.vector() {
+                Some(dim) => write!(f, "{}", dim),
+                None => write!(f, "{}", self.base),
+            }
+        }
+    }
+
+    /// What the checker knows about the value of an expression.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Type {
+        /// A quantity, of its dimension if that's known (it isn't for
+        /// `x^n` with a variable `n`)
+        Quantity(Option<Dimension>),
+        Bool,
+        /// Either, as for untyped parameters
+        Unknown,
+    }
+
+    impl Type {
+        fn of(value: &Value) -> Self {
+            match value {
+                Value::Quantity(qn) => Type::Quantity(Some(Dimension::of(&qn.term))),
+                Value::Bool(_) => Type::Bool,
+            }
+        }
+
+        /// The type of a value that's one or the other (`IF`'s branches).
+        fn join(self, other: Self) -> Self {
+            match (self, other) {
+                (lhs, rhs) if lhs == rhs => lhs,
+                (Type::Quantity(_), Type::Quantity(_)) => Type::Quantity(None),
+                _ => Type::Unknown,
+            }
+        }
+    }
+
+    impl fmt::Display for Type {
+        /// `quantity (L.T-1)`, `boolean`, ...
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Type::Quantity(Some(dim)) => write!(f, "quantity ({})", dim),
+                Type::Quantity(None) => write!(f, "quantity"),
+                Type::Bool => write!(f, "boolean"),
+                Type::Unknown => write!(f, "unknown"),
+            }
+        }
+    }
+
+    /// Checks statements one after the other. It keeps track of the units &
+    /// functions they define (in a copy of the environment), and of the
+    /// types of the variables they assign.
+    pub struct Checker {
+        env: Environment,
+        variables: HashMap<String, Type>,
+        /// The errors reported with each function's definition.
+        definitions: HashMap<String, Vec<CalculumError>>,
+        errors: Vec<CalculumError>,
+    }
+
+    impl Checker {
+        pub fn new(env: &Environment) -> Self {
+            Checker { env: env.clone(), variables: HashMap::new(), definitions: HashMap::new(), errors: vec![] }
+        }
+
+        /// Checks a statement, and infers the type of its value (if it's an
+        /// expression). Errors come all together, in the order of the input.
+        pub fn statement(&mut self, s: &str) -> Result<Option<Type>, Vec<CalculumError>> {
+            let t = match statement(s) {
+                Statement::Define(_) | Statement::Dimension(_) => {
+                    self.run(s);
+                    None
+                },
+                Statement::Function(signature) => {
+                    let name = signature.name.to_string();
+                    if self.run(s) {
+                        self.function(&name);
+                    }
+                    None
+                },
+                Statement::Assignment(name, pos) => {
+                    let t = self.parse(&s[pos..], pos).map_or(Type::Unknown, |expr| self.infer(&expr));
+                    self.variables.insert(name.to_string(), t);
+                    None
+                },
+                Statement::Expression => self.parse(s, 0).map(|expr| self.infer(&expr)),
+            };
+
+            let mut errors = std::mem::take(&mut self.errors);
+            errors.sort_by_key(|e| e.span().start);
+            if errors.is_empty() { Ok(t) } else { Err(errors) }
+        }
+
+        /// Runs a statement that only defines something (a unit or a
+        /// function), so needs no evaluating.
+        fn run(&mut self, s: &str) -> bool {
+            execute(s, &mut self.env).map_err(|e| self.errors.push(e)).is_ok()
+        }
+
+        fn parse(&mut self, s: &str, offset: usize) -> Option<Expr> {
+            parse(s, &self.env.registry)
+                .map(|expr| expr.offset(offset))
+                .map_err(|e| self.errors.push(e.offset(offset)))
+                .ok()
+        }
+
+        /// Checks a function's definition. The names its body uses that
+        /// aren't defined yet (other than its own) may be by the time it's
+        /// called, so they're left to the calls.
+        fn function(&mut self, name: &str) {
+            let Some(function) = self.env.function(name).cloned() else {
+                return
+            };
+
+            let errors: Vec<CalculumError> = self.signature(name, &function).into_iter()
+                .filter(|e| match e {
+                    CalculumError::UnknownVariable { .. } => false,
+                    CalculumError::UnknownFunction { name: other, .. } => other == name,
+                    _ => true,
+                })
+                .collect();
+            self.errors.extend(errors.iter().cloned());
+            self.definitions.insert(name.to_string(), errors);
+        }
+
+        /// The errors in a function's body, with its parameters' units,
+        /// & against the function's unit.
+        fn signature(&mut self, name: &str, function: &Function) -> Vec<CalculumError> {
+            let errors = self.errors.len();
+            let t = self.body(name, function, Self::params(function));
+
+            if let Some(unit) = &function.unit {
+                let dim = self.expect_quantity(t, &function.body.span);
+                self.commensurable(dim.as_ref(), Some(&Dimension::of(unit)), function.body.span.clone());
+            }
+            self.errors.drain(errors..).collect()
+        }
+
+        /// The parameters' types, from their units.
+        fn params(function: &Function) -> HashMap<String, Type> {
+            function.params.iter()
+                .map(|param| (param.name.clone(), param.unit.as_ref().map_or(Type::Unknown, |u| Type::Quantity(Some(Dimension::of(u))))))
+                .collect()
+        }
+
+        /// The type of a function's body. As at runtime, the body doesn't
+        /// see the function itself.
+        fn body(&mut self, name: &str, function: &Function, params: HashMap<String, Type>) -> Type {
+            let removed = self.env.functions.remove(name);
+            let mut scope = self.variables.clone();
+            scope.extend(params);
+            let variables = std::mem::replace(&mut self.variables, scope);
+
+            let t = self.infer(&function.body);
+
+            self.variables = variables;
+            if let Some(function) = removed {
+                self.env.define_function(name, function);
+            }
+            t
+        }
+
+        fn expect_quantity(&mut self, t: Type, span: &Span) -> Option<Dimension> {
+            match t {
+                Type::Quantity(dim) => dim,
+                Type::Bool => {
+                    self.errors.push(CalculumError::TypeMismatch {
+                        expected: "quantity".to_string(),
+                        found: "boolean".to_string(),
+                        span: span.clone()
+                    });
+                    None
+                },
+                Type::Unknown => None,
+            }
+        }
+
+        /// The dimension of an expression that has to be a quantity.
+        fn quantity(&mut self, expr: &Expr) -> Option<Dimension> {
+            let t = self.infer(expr);
+            self.expect_quantity(t, &expr.span)
+        }
+
+        /// Whether the dimensions match (when they're known). As at runtime,
+        /// arbitrary units get an error of their own. Returns `false` after
+        /// noting an error.
+        fn commensurable(&mut self, lhs: Option<&Dimension>, rhs: Option<&Dimension>, span: Span) -> bool {
+            let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+                return true
+            };
+            if lhs.base.units == rhs.base.units {
+                return true
+            }
+
+            self.errors.push(match (lhs.is_arbitrary(), rhs.is_arbitrary()) {
+                (true, _) => CalculumError::ArbitraryUnit { unit: lhs.to_string(), other: rhs.to_string(), span },
+                (_, true) => CalculumError::ArbitraryUnit { unit: rhs.to_string(), other: lhs.to_string(), span },
+                _ => CalculumError::Incommensurable { lhs: lhs.to_string(), rhs: rhs.to_string(), span },
+            });
+            false
+        }
+
+        fn dimensionless(&mut self, dim: Option<&Dimension>, span: Span) {
+            self.commensurable(dim, Some(&Dimension::dimensionless()), span);
+        }
+
+        /// As `+` & `-` (& `SUM`) at runtime: commensurable, & not in a
+        /// special unit.
+        fn addable(&mut self, lhs: Option<&Dimension>, rhs: Option<&Dimension>, span: Span) {
+            if !self.commensurable(lhs, rhs, span.clone()) {
+                return
+            }
+            if let Some(unit) = lhs.into_iter().chain(rhs).find_map(|dim| dim.special.clone()) {
+                self.errors.push(CalculumError::SpecialUnitAddition { unit, span });
+            }
+        }
+
+        /// As `interpreter::check_not_special`, with the dimensionless
+        /// quantities (outside of special units) for plain numbers.
+        fn scalable(&mut self, op: BinOp, lhs: Option<&Dimension>, rhs: Option<&Dimension>, span: Span) {
+            let special = match (lhs, rhs) {
+                (_, Some(rhs)) if rhs.special.is_some() && (op == BinOp::Div || lhs.is_some_and(|lhs| !lhs.is_plain())) => {
+                    rhs.special.clone()
+                },
+                (Some(lhs), Some(rhs)) if lhs.special.is_some() && !rhs.is_plain() => lhs.special.clone(),
+                _ => None,
+            };
+            if let Some(unit) = special {
+                self.errors.push(CalculumError::SpecialUnit { unit, span });
+            }
+        }
+
+        fn variable(&mut self, name: &str, span: Span) -> Type {
+            if let Some(t) = self.variables.get(name) {
+                return t.clone()
+            }
+            match self.env.get(name) {
+                Some(value) => Type::of(value),
+                None => {
+                    self.errors.push(CalculumError::UnknownVariable { name: name.to_string(), span });
+                    Type::Unknown
+                },
+            }
+        }
+
+        /// The dimension of `base^exp`, which depends on the exponent
+        /// (unless `base` is dimensionless). So it's only known for exponents
+        /// that don't depend on variables, e.g., `2` or `(1/3)`.
+        fn pow(&mut self, base: Option<Dimension>, exp: Option<Dimension>, exp_expr: &Expr, span: Span) -> Option<Dimension> {
+            self.dimensionless(exp.as_ref(), exp_expr.span.clone());
+            let base = base?;
+            if base.is_plain() {
+                return Some(base)
+            }
+
+            let constants = Environment { registry: self.env.registry.clone(), ..Environment::default() };
+            let one = UnitTerm::new("1", &constants.registry).ok()?;
+            let exp = eval(exp_expr, &constants).ok()?.as_quantity()?.convert_to(&one).ok()?.mag;
+            self.raise(base, exp, span)
+        }
+
+        /// As `interpreter::pow`, for a base that isn't a plain number: a
+        /// special unit can't be raised to a power, & the power can't leave
+        /// a fractional exponent (unless they're enabled).
+        fn raise(&mut self, base: Dimension, exp: f64, span: Span) -> Option<Dimension> {
+            let fractional_err = |base: &Dimension| CalculumError::FractionalExponent {
+                term: base.to_string(),
+                exponent: exp.to_string(),
+                span: span.clone()
+            };
+            let Some(rational) = Rational::approximate(exp, i8::MAX as i32) else {
+                self.errors.push(fractional_err(&base));
+                return None
+            };
+            if let Some(unit) = base.special.clone().filter(|_| rational != Rational::ONE) {
+                self.errors.push(CalculumError::SpecialUnit { unit, span });
+                return None
+            }
+
+            let dim = match base.pow(rational) {
+                Ok(dim) => dim,
+                Err(e) => {
+                    self.errors.push(e.at(span.clone()));
+                    return None
+                },
+            };
+            if dim.has_fractional_exponents() && !base.has_fractional_exponents() && !self.env.rational_exponents {
+                self.errors.push(fractional_err(&base));
+            }
+            Some(dim)
+        }
+
+        fn compare(&mut self, op: BinOp, lhs_expr: &Expr, rhs_expr: &Expr, span: Span) {
+            let (lhs, rhs) = (self.infer(lhs_expr), self.infer(rhs_expr));
+
+            if let (Type::Bool | Type::Unknown, Type::Bool | Type::Unknown, BinOp::Eq | BinOp::Ne) = (&lhs, &rhs, op) {
+                return
+            }
+
+            let lhs = self.expect_quantity(lhs, &lhs_expr.span);
+            let rhs = self.expect_quantity(rhs, &rhs_expr.span);
+            if op != BinOp::Commensurable {
+                self.commensurable(lhs.as_ref(), rhs.as_ref(), span);
+            }
+        }
+
+        fn call_user(&mut self, name: &str, function: &Function, args: &[Expr], span: Span) -> Type {
+            let mut params = HashMap::new();
+            for (param, arg) in function.params.iter().zip(args) {
+                let t = match &param.unit {
+                    Some(unit) => {
+                        let dim = self.quantity(arg);
+                        self.commensurable(dim.as_ref(), Some(&Dimension::of(unit)), arg.span.clone());
+                        Type::Quantity(Some(Dimension::of(unit)))
+                    },
+                    None => self.infer(arg),
+                };
+                params.insert(param.name.clone(), t);
+            }
+
+            let arity = function.params.len();
+            if let Err(e) = check_arity(name, args, arity..=arity, &span) {
+                self.errors.push(e);
+                return Type::Unknown
+            }
+
+            // The body is checked again with the names defined since (&,
+            // without a unit, with these arguments, which its type depends
+            // on). The errors that weren't reported with its definition are
+            // reported at the call.
+            let mut caused = self.signature(name, function);
+            let t = match &function.unit {
+                Some(unit) => Type::Quantity(Some(Dimension::of(unit))),
+                None => {
+                    let errors = self.errors.len();
+                    let t = self.body(name, function, params);
+                    caused.extend(self.errors.drain(errors..));
+                    t
+                },
+            };
+
+            let reported = self.definitions.get(name).map_or(&[][..], |errors| errors.as_slice());
+            let mut errors: Vec<CalculumError> = vec![];
+            for e in caused.into_iter().filter(|e| !reported.contains(e)).map(|e| e.at(span.clone())) {
+                if !errors.contains(&e) {
+                    errors.push(e);
+                }
+            }
+            self.errors.extend(errors);
+            t
+        }
+
+        /// The built-ins' signatures (see `interpreter::call`).
+        fn call(&mut self, name: &str, args: &[Expr], span: Span) -> Type {
+            if let Some(function) = self.env.function(name).cloned() {
+                return self.call_user(name, &function, args, span)
+            }
+
+            let upper = name.to_uppercase();
+            let arity: RangeInclusive<usize> = match upper.as_str() {
+                "SQRT" | "ABS" | "SIN" | "COS" | "TAN" | "EXP" | "LN" | "LOG10" => 1..=1,
+                "SUM" | "AVERAGE" | "MIN" | "MAX" => 1..=usize::MAX,
+                "ROUND" => 1..=3,
+                "FLOOR" | "CEILING" => 1..=2,
+                "IF" => 2..=3,
+                _ => {
+                    args.iter().for_each(|arg| { self.infer(arg); });
+                    self.errors.push(CalculumError::UnknownFunction { name: name.to_string(), span });
+                    return Type::Unknown
+                },
+            };
+            if let Err(e) = check_arity(name, args, arity, &span) {
+                args.iter().for_each(|arg| { self.infer(arg); });
+                self.errors.push(e);
+                return Type::Unknown
+            }
+
+            match upper.as_str() {
+                "SQRT" => Type::Quantity(match self.quantity(&args[0]) {
+                    Some(dim) if dim.is_plain() => Some(dim),
+                    Some(dim) => self.raise(dim, 0.5, span),
+                    None => None,
+                }),
+                "SUM" => {
+                    let first = self.quantity(&args[0]);
+                    for arg in &args[1..] {
+                        let dim = self.quantity(arg);
+                        self.addable(first.as_ref(), dim.as_ref(), arg.span.clone());
+                    }
+                    Type::Quantity(first)
+                },
+                "AVERAGE" | "MIN" | "MAX" => {
+                    let mut first = None;
+                    for arg in args {
+                        let dim = self.quantity(arg);
+                        self.commensurable(first.as_ref(), dim.as_ref(), arg.span.clone());
+                        first = first.or(dim);
+                    }
+                    Type::Quantity(first)
+                },
+                "ABS" => Type::Quantity(self.quantity(&args[0])),
+                "ROUND" => {
+                    let dim = self.quantity(&args[0]);
+                    if let Some(digits) = args.get(1) {
+                        let digits_dim = self.quantity(digits);
+                        self.dimensionless(digits_dim.as_ref(), digits.span.clone());
+                    }
+                    match args.get(2).map(|unit| unit_arg(unit, &self.env)) {
+                        Some(Ok(target)) => {
+                            let target = Dimension::of(&target);
+                            self.commensurable(dim.as_ref(), Some(&target), span);
+                            Type::Quantity(Some(target))
+                        },
+                        Some(Err(e)) => {
+                            self.errors.push(e);
+                            Type::Quantity(None)
+                        },
+                        None => Type::Quantity(dim),
+                    }
+                },
+                "FLOOR" | "CEILING" => {
+                    let dim = self.quantity(&args[0]);
+                    if let Some(step) = args.get(1) {
+                        let step_dim = self.quantity(step);
+                        self.commensurable(dim.as_ref(), step_dim.as_ref(), step.span.clone());
+                    }
+                    Type::Quantity(dim)
+                },
+                "SIN" | "COS" | "TAN" => {
+                    let dim = self.quantity(&args[0]).filter(|dim| dim.vector() != Some(DimensionVector::ANGLE));
+                    self.dimensionless(dim.as_ref(), args[0].span.clone());
+                    Type::Quantity(Some(Dimension::dimensionless()))
+                },
+                "EXP" | "LN" | "LOG10" => {
+                    let dim = self.quantity(&args[0]);
+                    self.dimensionless(dim.as_ref(), args[0].span.clone());
+                    Type::Quantity(Some(Dimension::dimensionless()))
+                },
+                _ => {
+                    if let Type::Quantity(_) = self.infer(&args[0]) {
+                        self.errors.push(CalculumError::TypeMismatch {
+                            expected: "boolean".to_string(),
+                            found: "quantity".to_string(),
+                            span: args[0].span.clone()
+                        });
+                    }
+                    let then = self.infer(&args[1]);
+                    let otherwise = args.get(2).map_or(Type::Bool, |arg| self.infer(arg));
+                    then.join(otherwise)
+                },
+            }
+        }
+
+        /// The type of an expression, noting any errors in it (& carrying on
+        /// past them).
+        pub fn infer(&mut self, expr: &Expr) -> Type {
+            let span = expr.span.clone();
+
+            match &expr.kind {
+                ExprKind::Quantity(qn) => Type::Quantity(Some(Dimension::of(&qn.term))),
+                ExprKind::Term(term) => Type::Quantity(Some(Dimension::of(term))),
+                ExprKind::Variable(name) => self.variable(name, span),
+                ExprKind::Neg(operand) => Type::Quantity(self.quantity(operand)),
+                ExprKind::Call(name, args) => self.call(name, args, span),
+                ExprKind::Convert(operand, target) => {
+                    let dim = self.quantity(operand);
+                    let target = Dimension::of(target);
+                    self.commensurable(dim.as_ref(), Some(&target), span);
+                    Type::Quantity(Some(target))
+                },
+                ExprKind::Binary(op, lhs_expr, rhs_expr) if op.is_comparison() => {
+                    self.compare(*op, lhs_expr, rhs_expr, span);
+                    Type::Bool
+                },
+                ExprKind::Binary(op, lhs_expr, rhs_expr) => {
+                    let (lhs, rhs) = (self.quantity(lhs_expr), self.quantity(rhs_expr));
+
+                    Type::Quantity(match op {
+                        BinOp::Add | BinOp::Sub => {
+                            self.addable(lhs.as_ref(), rhs.as_ref(), span);
+                            lhs.or(rhs)
+                        },
+                        BinOp::Mul | BinOp::Div => {
+                            self.scalable(*op, lhs.as_ref(), rhs.as_ref(), span.clone());
+                            lhs.zip(rhs)
+                                .map(|(lhs, rhs)| if *op == BinOp::Mul { lhs.product(rhs) } else { lhs.quotient(rhs) })
+                                .transpose()
+                                .unwrap_or_else(|e| {
+                                    self.errors.push(e.at(span));
+                                    None
+                                })
+                        },
+                        BinOp::Pow => self.pow(lhs, rhs, rhs_expr, span),
+                        _ => unreachable!("{:?} is a comparison", op),
+                    })
+                },
+            }
+        }
+    }
+
+    /// Checks a script (one statement per line) against `env`, which it
+    /// leaves as it is. The errors' spans are into the whole script.
+    pub fn check(script: &str, env: &Environment) -> Vec<CalculumError> {
+        let mut checker = Checker::new(env);
+        let mut errors = vec![];
+        let mut start = 0;
+
+        for line in script.split_inclusive('\n') {
+            let statement = line.trim_end_matches(['\n', '\r']);
+            if !statement.trim().is_empty() {
+                if let Err(errs) = checker.statement(statement) {
+                    errors.extend(errs.into_iter().map(|e| e.offset(start)));
+                }
+            }
+            start += line.len();
+        }
+        errors
+    }
+}
+
+pub use checker::{check, Checker, Dimension, Type};
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ucum::error::CalculumError;
+    use super::*;
+
+    fn interpret(s: &str) -> Result<Quantity> {
+        super::interpret(s, &Environment::new()).map(|value| match value {
+            Value::Quantity(qn) => qn,
+            value => panic!("{}: {:?} isn't a quantity", s, value),
+        })
+    }
+
+    fn execute_quantity(s: &str, env: &mut Environment) -> Quantity {
+        match execute(s, env) {
+            Ok(Some(Value::Quantity(qn))) => qn,
+            result => panic!("{}: {:?} isn't a quantity", s, result),
+        }
+    }
+
+    fn tokens(s: &str) -> Vec<tokenizer::Token> {
+        tokenizer::Tokenizer::new(s, &UnitRegistry::default()).map(|t| t.unwrap().0).collect()
+    }
+
+    fn quantity(mag: f64, term: &str) -> tokenizer::Token {
+        tokenizer::Token::Quantity(Quantity::new(mag, term, &UnitRegistry::default()).unwrap())
+    }
+
+    #[test]
+    fn it_reads_the_next_quantity() {
+        assert_eq!(tokens("1 kg.m/s2")[0], quantity(1., "kg.m/s2"));
+    }
+
+    #[test]
+    fn it_ignores_spaces_bw_mag_and_term() {
+        assert_eq!(tokens("1 kg.m/s2"), tokens("1kg.m/s2"));
+    }
+
+    #[test]
+    fn it_separates_tokens_with_any_whitespace() {
+        assert_eq!(tokens("1\tkg.m/s2\t+ 5 kg.m/s2\r\n"), tokens("1 kg.m/s2 + 5 kg.m/s2"));
+        assert_eq!(tokens("2 m\tin\tft"), tokens("2 m in ft"));
+
+        let mut env = Environment::new();
+        assert_eq!(execute("define\tfur\t= 201.168 m", &mut env), Ok(None));
+        let qn = execute_quantity("1\tfur\t->\tm", &mut env);
+        assert!((qn.mag - 201.168).abs() < 1e-9, "{:?}", qn);
+    }
+
+    #[test]
+    fn it_reads_add() {
+        let ts = tokens("1 kg.m/s2 + 5 kg.m/s2");
+
+        assert_eq!(ts, vec![
+            quantity(1., "kg.m/s2"),
+            tokenizer::Token::Add,
+            quantity(5., "kg.m/s2")
+        ])
+    }
+
+    #[test]
+    fn it_reads_sub() {
+        let ts = tokens("1 kg.m/s2 - 5 kg.m/s2");
+
+        assert_eq!(ts, vec![
+            quantity(1., "kg.m/s2"),
+            tokenizer::Token::Sub,
+            quantity(5., "kg.m/s2")
+        ])
+    }
+
+    #[test]
+    fn it_reads_mul() {
+        let ts = tokens("1 kg.m/s2 * 5 s2/kg/m");
+
+        assert_eq!(ts, vec![
+            quantity(1., "kg.m/s2"),
+            tokenizer::Token::Mul,
+            quantity(5., "s2/kg/m")
+        ])
+    }
+
+    #[test]
+    fn it_reads_div() {
+        let ts = tokens("1 kg.m/s2 / 5 kg.m/s2");
+
+        assert_eq!(ts, vec![
+            quantity(1., "kg.m/s2"),
+            tokenizer::Token::Div,
+            quantity(5., "kg.m/s2")
+        ])
+    }
+
+    // -- Interpreter
+
+    #[test]
+    fn it_reads_terms_with_powers_of_ten_and_parentheses() {
+        assert_eq!(tokens("1 10^3.kg"), vec![quantity(1., "10^3.kg")]);
+        assert_eq!(tokens("1 10*-3.kg"), vec![quantity(1., "10*-3.kg")]);
+        assert_eq!(tokens("1 (m)"), vec![quantity(1., "(m)")]);
+        assert_eq!(tokens("2 (kg/(m.s2))"), vec![quantity(2., "(kg/(m.s2))")]);
+
+        assert_interprets("1 10^3.kg -> kg", 1000., "kg");
+        assert_interprets("1 10*3.kg -> g", 1e6, "g");
+        assert_interprets("1 (m) -> cm", 100., "cm");
+        assert_interprets("2 (m/s) * (3 s)", 6., "m");
+        assert!(matches!(interpret("2 (3 + 4)"), Err(CalculumError::Syntax { .. })));
+    }
+
+    #[test]
+    fn it_requires_separator_between_quantities() {
+        let err = interpret("1 kg.m/s 2 s/m").unwrap_err();
+
+        assert!(matches!(err, CalculumError::Syntax { .. }));
+        assert_eq!(err.span(), &(9..14));
+    }
+
+    #[test]
+    fn it_multiplies_quantities() {
+        assert_eq!(interpret("1 kg.m/s2 * 2 s/m"), Quantity::new(2., "kg/s", &UnitRegistry::default()));
+    }
+
+    #[test]
+    fn it_divides_quantities() {
+        assert_eq!(interpret("1 kg.m/s2 / 2 m/s"), Quantity::new(0.5, "kg/s", &UnitRegistry::default()));
+    }
+
+    #[test]
+    fn it_adds_quantities_with_the_same_units() {
+        assert_eq!(interpret("1 kg.m/s2 + 5 N"), Quantity::new(6., "N", &UnitRegistry::default()))
+    }
+
+    #[test]
+    fn it_subs_quantities_with_the_same_units() {
+        assert_eq!(interpret("10 kg.m/s2 - 5 N"), Quantity::new(5., "N", &UnitRegistry::default()))
+    }
+
+    #[test]
+    fn it_doesnt_add_quantities_with_different_units() {
+        let err = interpret("10 kg.m/s + 5 N").unwrap_err();
+
+        assert!(matches!(err, CalculumError::Incommensurable { .. }));
+        assert_eq!(err.span(), &(0..15));
+    }
+
+    #[test]
+    fn it_doesnt_sub_quantities_with_different_units() {
+        let err = interpret("10 kg.m/s2 - 5 kg/m.s2").unwrap_err();
+
+        assert!(matches!(err, CalculumError::Incommensurable { .. }));
+    }
+
+    #[test]
+    fn it_doesnt_divide_by_zero() {
+        let err = interpret("10 kg / 0 s").unwrap_err();
+
+        assert_eq!(err, CalculumError::DivisionByZero { span: 8..11 });
+    }
+
+    #[test]
+    fn it_reports_invalid_tokens() {
+        let err = interpret("10 kg + $").unwrap_err();
+
+        assert!(matches!(err, CalculumError::Syntax { .. }));
+        assert_eq!(err.span(), &(8..9));
+    }
+
+    #[test]
+    fn it_offsets_errors_in_unit_terms() {
+        let err = interpret("1 kg + 2 k[in_i]").unwrap_err();
+
+        assert_eq!(err, CalculumError::PrefixNotAllowed {
+            prefix: "k".to_string(),
+            atom: "[in_i]".to_string(),
+            span: 9..16
+        });
+
+        let err = interpret("1 kg + 2 [foo]").unwrap_err();
+
+        assert_eq!(err, CalculumError::UnknownAtom { atom: "[foo]".to_string(), span: 9..14 });
+    }
+
+    // -- Conversions
+
+    fn unit_term(s: &str) -> term::UnitTerm {
+        term::UnitTerm::new(s, &UnitRegistry::default()).unwrap()
+    }
+
+    fn assert_converts(from: Quantity, to: &str, mag: f64) {
+        let converted = from.convert_to(&unit_term(to)).unwrap();
+
+        assert!((converted.mag - mag).abs() <= 1e-9 * mag.abs(), "{} != {}", converted.mag, mag);
+        assert_eq!(converted.term.to_string(), to);
+    }
+
+    #[test]
+    fn it_converts_commensurable_quantities() {
+        assert_converts(Quantity::new(1., "[mi_i]/h", &UnitRegistry::default()).unwrap(), "m/s", 0.44704);
+        assert_converts(Quantity::new(1., "km", &UnitRegistry::default()).unwrap(), "m", 1000.);
+        assert_converts(Quantity::new(10., "m/s2", &UnitRegistry::default()).unwrap(), "[mi_i]/h2", 80529.70651396);
+        assert_converts(Quantity::new(1., "N", &UnitRegistry::default()).unwrap(), "kg.m/s2", 1.);
+        assert_converts(Quantity::new(180., "deg", &UnitRegistry::default()).unwrap(), "rad", std::f64::consts::PI);
+    }
+
+    #[test]
+    fn it_doesnt_convert_incommensurable_quantities() {
+        let err = Quantity::new(1., "m/s", &UnitRegistry::default()).unwrap().convert_to(&unit_term("s")).unwrap_err();
+
+        assert_eq!(err, CalculumError::Incommensurable {
+            lhs: "L.T-1".to_string(),
+            rhs: "T".to_string(),
+            span: 0..0
+        });
+    }
+
+    #[test]
+    fn it_names_dimensions_in_incommensurable_errors() {
+        let err = interpret("1 m + 1 s").unwrap_err();
+        assert_eq!(err.message(), "[Dimension Error] Units 'L' and 'T' are not commensurable.");
+
+        let mut env = Environment::new();
+        execute("dimension [currency]", &mut env).unwrap();
+        let err = execute("1 [currency] -> m", &mut env).unwrap_err();
+        assert_eq!(err.message(), "[Dimension Error] Units '[currency]' and 'L' are not commensurable.");
+    }
+
+    #[test]
+    fn it_reads_conversions() {
+        let ts = tokens("10 m/s2 -> [mi_i]/h2");
+
+        assert_eq!(ts, vec![
+            quantity(10., "m/s2"),
+            tokenizer::Token::Convert,
+            tokenizer::Token::Term(unit_term("[mi_i]/h2"))
+        ]);
+
+        let ts = tokens("3 [ft_i] in cm");
+
+        assert_eq!(ts, vec![
+            quantity(3., "[ft_i]"),
+            tokenizer::Token::Convert,
+            tokenizer::Token::Term(unit_term("cm"))
+        ]);
+    }
+
+    #[test]
+    fn it_interprets_conversions() {
+        let qn = interpret("10 m/s2 -> [mi_i]/h2").unwrap();
+        assert_eq!(qn.to_string(), format!("{} '[mi_i]/h2'", qn.mag));
+        assert!((qn.mag - 80529.70651396).abs() < 1e-6);
+
+        let qn = interpret("3 [ft_i] in cm").unwrap();
+        assert!((qn.mag - 91.44).abs() < 1e-9);
+
+        // Conversions apply to everything on their left
+        let qn = interpret("1 m * 2 -> cm").unwrap();
+        assert!((qn.mag - 200.).abs() < 1e-9);
     }
 
     #[test]
-    #[should_panic]
-    fn it_doesnt_sub_quantities_with_different_units() {
-        println!("{}", interpret("10 kg.m/s2 - 5 kg/m.s2"));
+    fn it_doesnt_convert_to_incommensurable_units() {
+        let err = interpret("10 m/s2 -> [mi_i]/h").unwrap_err();
+
+        assert!(matches!(err, CalculumError::Incommensurable { .. }));
+        assert_eq!(err.span(), &(0..19));
+
+        let err = interpret("10 m/s2 ->").unwrap_err();
+
+        assert!(matches!(err, CalculumError::Syntax { .. }));
+    }
+
+    fn assert_interprets(s: &str, mag: f64, term: &str) {
+        let qn = interpret(s).unwrap();
+
+        assert!((qn.mag - mag).abs() <= 1e-9 * mag.abs(), "{}: {} != {}", s, qn.mag, mag);
+        assert_eq!(qn.term.to_string(), term, "{}", s);
+    }
+
+    #[test]
+    fn it_converts_before_adding() {
+        assert_interprets("1 km + 1 m", 1.001, "km");
+        assert_interprets("1 m + 1 km", 1001., "m");
+        assert_interprets("1 [ft_i] + 6 [in_i]", 1.5, "[ft_i]");
+        assert_interprets("1 h + 30 min + 60 s", 1.51666666666, "h");
+    }
+
+    #[test]
+    fn it_converts_before_subtracting() {
+        assert_interprets("1 km - 1 m", 0.999, "km");
+        assert_interprets("1 [lb_av] - 1 [oz_av]", 0.9375, "[lb_av]");
+    }
+
+    #[test]
+    fn it_includes_factors_in_unit_terms() {
+        // `m.kg/5s2` is a fifth of a newton
+        assert_interprets("1 m.kg/5s2 + 1 N", 6., "m.kg/5s2");
+        assert_interprets("1 N - 5 m.kg/5s2", 0., "N");
+        assert_interprets("1 m.10^3 + 1 km", 2., "m.10^3");
+    }
+
+    #[test]
+    fn it_keeps_factors_in_the_magnitude_of_products() {
+        // `m/5s` times `s` is a fifth of a metre, whichever way it's shown
+        assert_interprets("1 m/5s * 1 s", 0.2, "m");
+        assert_interprets("1 m/5s * 1 s -> m", 0.2, "m");
+        assert_eq!(interpret("1 m/5s * 1 s").unwrap().to_string(), "0.2 'm'");
+
+        assert_interprets("2 km/5h / (1 m/10s) * 1 m", 4., "h-1.km.s");
+        assert_interprets("2 km/5h / (1 m/10s) * 1 m -> m", 4. / 3.6, "m");
+        assert_interprets("(1 m/4s)^2", 0.0625, "m2.s-2");
+    }
+
+    #[test]
+    fn it_converts_special_units() {
+        assert_interprets("37 Cel -> [degF]", 98.6, "[degF]");
+        assert_interprets("98.6 [degF] -> K", 310.15, "K");
+        assert_interprets("100 Cel in [degRe]", 80., "[degRe]");
+        assert_interprets("7.4 [pH] -> mol/l", 3.981071705534969e-8, "mol/l");
+        assert_interprets("20 B[SPL] -> Pa", 2e5, "Pa");
+        assert_interprets("100 Pa -> dB[SPL]", 133.97940008672037, "dB[SPL]");
+        assert_interprets("3 Np -> B", 1.3028834457, "B");
+    }
+
+    #[test]
+    fn it_rejects_products_of_special_units() {
+        assert_interprets("37 Cel * 2", 74., "Cel");
+        assert_interprets("2 * 37 Cel -> K", 347.15, "K");
+        assert_interprets("37 Cel / 2", 18.5, "Cel");
+        let err = interpret("2 m * 37 Cel").unwrap_err();
+        assert_eq!(err, CalculumError::SpecialUnit { unit: "Cel".to_string(), span: 0..12 });
+        assert_eq!(err.message(), "[Unit Error] The special unit 'Cel' can't be multiplied, divided or raised to a power.");
+        assert!(matches!(interpret("2 / 1 Cel"), Err(CalculumError::SpecialUnit { .. })));
+        assert_eq!(
+            interpret("37 Cel * 2 m").unwrap_err(),
+            CalculumError::SpecialUnit { unit: "Cel".to_string(), span: 0..12 }
+        );
+        assert!(matches!(interpret("2 m / 1 Cel"), Err(CalculumError::SpecialUnit { .. })));
+        assert!(matches!(interpret("1 Cel.m"), Err(CalculumError::SpecialUnit { span, .. }) if span == (2..7)));
+        assert!(matches!(interpret("1 [pH]2"), Err(CalculumError::SpecialUnit { .. })));
+    }
+
+    #[test]
+    fn it_checks_special_units_for_commensurability() {
+        assert!(matches!(interpret("1 Cel -> m"), Err(CalculumError::Incommensurable { .. })));
+        assert!(matches!(interpret("1 Cel + 1 m"), Err(CalculumError::Incommensurable { .. })));
+    }
+
+    #[test]
+    fn it_doesnt_add_special_units() {
+        // `37 Cel + 1 K` could be 38 Cel or 311.15 K + 1 K
+        let err = interpret("37 Cel + 1 K").unwrap_err();
+        assert_eq!(err, CalculumError::SpecialUnitAddition { unit: "Cel".to_string(), span: 0..12 });
+        assert_eq!(
+            err.message(),
+            "[Unit Error] The special unit 'Cel' can't be added or subtracted; convert to its base units first."
+        );
+        assert!(matches!(interpret("1 Cel + 1 Cel"), Err(CalculumError::SpecialUnitAddition { .. })));
+        assert!(matches!(interpret("300 K - 20 Cel"), Err(CalculumError::SpecialUnitAddition { .. })));
+        assert_eq!(
+            interpret("100 [degF] - 1 K").unwrap_err(),
+            CalculumError::SpecialUnitAddition { unit: "[degF]".to_string(), span: 0..16 }
+        );
+        assert!(matches!(interpret("SUM(1 K, 37 Cel)"), Err(CalculumError::SpecialUnitAddition { span, .. }) if span == (9..15)));
+        assert_interprets("AVERAGE(10 Cel, 20 Cel)", 15., "Cel");
+        assert_interprets("(37 Cel -> K) - 1 K", 309.15, "K");
+    }
+
+    #[test]
+    fn it_adds_without_panicking() {
+        let registry = UnitRegistry::default();
+        let km = Quantity::new(1., "km", &registry).unwrap();
+
+        assert_eq!(km.clone().checked_add(&Quantity::new(1., "m", &registry).unwrap()), Quantity::new(1.001, "km", &registry));
+        assert_eq!(km.clone().checked_sub(&Quantity::new(1., "km", &registry).unwrap()), Quantity::new(0., "km", &registry));
+        assert!(matches!(km.checked_add(&Quantity::new(1., "s", &registry).unwrap()), Err(CalculumError::Incommensurable { .. })));
+    }
+
+    #[test]
+    fn it_only_converts_arbitrary_units_to_themselves() {
+        assert_interprets("5 m[iU]/mL -> [iU]/L", 5., "[iU]/L");
+        assert_interprets("1 k[iU] + 1 [IU]", 1.001, "k[iU]");
+        assert_interprets("2 [arb'U] - 1 [arb'U]", 1., "[arb'U]");
+
+        assert_eq!(
+            interpret("1 [iU] -> [CFU]").unwrap_err(),
+            CalculumError::ArbitraryUnit { unit: "[iU]".to_string(), other: "[CFU]".to_string(), span: 0..15 }
+        );
+        assert!(matches!(interpret("1 [iU] -> mol"), Err(CalculumError::ArbitraryUnit { .. })));
+        assert!(matches!(interpret("1 m + 1 [PFU]"), Err(CalculumError::ArbitraryUnit { .. })));
+        assert!(matches!(interpret("1 [arb'U] -> 1"), Err(CalculumError::ArbitraryUnit { .. })));
+    }
+
+    #[test]
+    fn it_interprets_user_defined_units() {
+        let mut env = Environment::new();
+        env.registry.define_unit("[pallet]", 40., "[ft_i]3", false).unwrap();
+        env.registry.define_unit("fur", 201.168, "m", true).unwrap();
+        env.registry.define_prefix("Mx", 1e6).unwrap();
+
+        let qn = execute_quantity("2 [pallet] -> m3", &mut env);
+        assert!((qn.mag - 2.26534772736).abs() < 1e-9, "{:?}", qn);
+
+        let qn = execute_quantity("1 kfur + 1 Mxm -> km", &mut env);
+        assert!((qn.mag - 1201.168).abs() < 1e-9, "{:?}", qn);
+
+        assert!(matches!(
+            super::interpret("1 k[pallet]", &env),
+            Err(CalculumError::PrefixNotAllowed { .. })
+        ));
+        // The definitions only exist in the registry they were made in
+        assert!(matches!(interpret("1 [pallet]"), Err(CalculumError::UnknownAtom { .. })));
+    }
+
+    #[test]
+    fn it_executes_definitions() {
+        let mut env = Environment::new();
+
+        assert_eq!(execute("define [pallet] = 40 [ft_i]3", &mut env), Ok(None));
+        assert_eq!(execute("define fur = 201.168 m", &mut env), Ok(None));
+
+        let qn = execute_quantity("2 [pallet] -> m3", &mut env);
+        assert!((qn.mag - 2.26534772736).abs() < 1e-9, "{:?}", qn);
+        let qn = execute_quantity("1 kfur in m", &mut env);
+        assert!((qn.mag - 201168.).abs() < 1e-9, "{:?}", qn);
+    }
+
+    #[test]
+    fn it_executes_new_dimensions() {
+        let mut env = Environment::new();
+
+        execute("dimension [currency]", &mut env).unwrap();
+        execute("define [EUR] = 1 [currency]", &mut env).unwrap();
+        execute("define [USD] = 0.92 [EUR]", &mut env).unwrap();
+
+        let qn = execute_quantity("100 [USD] + 8 [EUR] -> [EUR]", &mut env);
+        assert!((qn.mag - 100.).abs() < 1e-9, "{:?}", qn);
+        let qn = execute_quantity("2 [USD]/kg * 3 kg", &mut env);
+        assert!(qn.term.is_commensurable(&term::UnitTerm::new("[EUR]", &env.registry).unwrap()));
+
+        for s in ["1 [EUR] -> 1", "1 [EUR] + 1 m", "1 [EUR] -> mol", "1 [EUR]/m -> [EUR]"] {
+            assert!(matches!(execute(s, &mut env), Err(CalculumError::Incommensurable { .. })), "{}", s);
+        }
+    }
+
+    #[test]
+    fn it_reports_errors_in_definitions() {
+        let mut env = Environment::new();
+
+        assert_eq!(
+            execute("define m = 3 [ft_i]", &mut env),
+            Err(CalculumError::AlreadyDefined { name: "m".to_string(), span: 7..8 })
+        );
+        assert!(matches!(
+            execute("define [x] = 3 [foo]", &mut env),
+            Err(CalculumError::UnknownAtom { span, .. }) if span == (15..20)
+        ));
+        assert!(matches!(
+            execute("define [x] 3 m", &mut env),
+            Err(CalculumError::Syntax { span, .. }) if span == (11..11)
+        ));
+        assert!(matches!(
+            execute("define [x] = 3 m + 1 m", &mut env),
+            Err(CalculumError::Syntax { span, .. }) if span == (12..22)
+        ));
+        assert_eq!(
+            execute("define [warm] = 1 Cel", &mut env),
+            Err(CalculumError::SpecialUnitDefinition { unit: "Cel".to_string(), span: 15..21 })
+        );
+        assert!(matches!(
+            execute("dimension [x] [y]", &mut env),
+            Err(CalculumError::Syntax { .. })
+        ));
+        assert!(matches!(
+            execute("dimension [ft_i]", &mut env),
+            Err(CalculumError::AlreadyDefined { span, .. }) if span == (10..16)
+        ));
+    }
+
+    #[test]
+    fn it_assigns_variables() {
+        let mut env = Environment::new();
+
+        assert_eq!(execute("accel = 10 m/s2", &mut env), Ok(None));
+        let qn = execute_quantity("15 m.kg/s2 / accel", &mut env);
+        assert_eq!(qn, Quantity::new(1.5, "kg", &env.registry).unwrap());
+
+        // Variables can be reassigned, including in terms of themselves
+        execute("accel = accel * 2", &mut env).unwrap();
+        let qn = execute_quantity("accel -> m/s2", &mut env);
+        assert_eq!(qn.mag, 20.);
+    }
+
+    #[test]
+    fn it_keeps_variables_apart_from_units() {
+        let mut env = Environment::new();
+        execute("m = 3 s", &mut env).unwrap();
+
+        assert_eq!(execute_quantity("2 m -> cm", &mut env).mag, 200.);
+        assert_eq!(execute_quantity("m -> ms", &mut env).mag, 3000.);
+        assert!(matches!(
+            execute("m + 1 m", &mut env),
+            Err(CalculumError::Incommensurable { .. })
+        ));
+    }
+
+    #[test]
+    fn it_reports_unknown_variables() {
+        let mut env = Environment::new();
+
+        assert_eq!(
+            execute("1 m + accel", &mut env),
+            Err(CalculumError::UnknownVariable { name: "accel".to_string(), span: 6..11 })
+        );
+        assert_eq!(
+            execute("v = 2 * speed", &mut env),
+            Err(CalculumError::UnknownVariable { name: "speed".to_string(), span: 8..13 })
+        );
+        assert_eq!(env.get("v"), None);
+    }
+
+    // -- Precedence
+
+    #[test]
+    fn it_multiplies_before_adding() {
+        assert_interprets("1 m + 2 m * 3", 7., "m");
+        assert_interprets("1 m + 6 m / 3 - 1 m", 2., "m");
+        assert_interprets("2 * 3 + 4 * 5", 26., "");
+    }
+
+    #[test]
+    fn it_groups_with_parentheses() {
+        assert_interprets("(1 m + 2 m) * 3", 9., "m");
+        assert_interprets("2 m * (3 s + 1 min)", 126., "m.s");
+        assert_interprets("((2 km))", 2., "km");
+        assert_interprets("(1 m + 50 cm -> cm) * 2", 300., "cm");
+    }
+
+    #[test]
+    fn it_negates() {
+        assert_interprets("-3 m", -3., "m");
+        assert_interprets("1 m - -2 m", 3., "m");
+        assert_interprets("-(1 m + 2 m) * 2", -6., "m");
+        assert_interprets("-2^2", -4., "");
+    }
+
+    #[test]
+    fn it_raises_quantities_to_integer_powers() {
+        assert_interprets("(3 m)^2", 9., "m2");
+        assert_interprets("2 * 3 m ^ 2 / 3", 6., "m2");
+        assert_interprets("(2 m/s)^-1 -> s/m", 0.5, "s/m");
+        assert_interprets("2^3^2", 512., "");
+        assert_interprets("4^0.5", 2., "");
+        assert_interprets("(2 m)^0", 1., "");
+    }
+
+    #[test]
+    fn it_rejects_invalid_powers() {
+        assert_eq!(
+            interpret("(2 m)^0.5").unwrap_err(),
+            CalculumError::FractionalExponent { term: "m".to_string(), exponent: "0.5".to_string(), span: 0..9 }
+        );
+        assert!(matches!(
+            interpret("2^(1 m)"),
+            Err(CalculumError::Incommensurable { span, .. }) if span == (2..7)
+        ));
+        assert!(matches!(interpret("(1 Cel)^2"), Err(CalculumError::SpecialUnit { .. })));
+        assert!(matches!(interpret("(1 m2)^100"), Err(CalculumError::ExponentOverflow { .. })));
+    }
+
+    #[test]
+    fn it_keeps_unit_exponents_in_range_in_products() {
+        let overflow = |s: &str| match interpret(s) {
+            Err(CalculumError::ExponentOverflow { span, .. }) => span,
+            result => panic!("{}: {:?} isn't an exponent overflow", s, result),
+        };
+
+        assert_eq!(overflow("1 m^(1/127).m^(1/113).m^(1/109).m^(1/107).m^(1/103)"), 2..51);
+        assert_eq!(overflow("1 m^(1/127) * 1 m^(1/113) * 1 m^(1/109) * 1 m^(1/107) * 1 m^(1/103)"), 0..25);
+        assert_eq!(overflow("1 m^(127) * 1 m^(127)"), 0..21);
+        assert_eq!(overflow("1 m127 / 1 m-1"), 0..14);
+        assert_eq!(overflow("1 m127 -> m254"), 11..14);
+        assert_interprets("1 m^(127) / 1 m^(127)", 1., "");
+    }
+
+    #[test]
+    fn it_keeps_powers_in_the_reals() {
+        assert_eq!(
+            interpret("sqrt(-4)").unwrap_err(),
+            CalculumError::OutOfDomain { function: "sqrt".to_string(), value: "-4".to_string(), span: 0..8 }
+        );
+        assert!(matches!(interpret("(-8)^(1/3)"), Err(CalculumError::OutOfDomain { .. })));
+        assert!(matches!(interpret("(-8 m3)^(1/3)"), Err(CalculumError::OutOfDomain { .. })));
+        assert_eq!(interpret("2^1000000").unwrap_err(), CalculumError::Overflow { span: 0..9 });
+        assert!(matches!(interpret("(10^200 * 1 m)^2"), Err(CalculumError::Overflow { .. })));
+        assert!(matches!(interpret("0^-1"), Err(CalculumError::DivisionByZero { .. })));
+        assert_interprets("(-2)^3", -8., "");
+        assert_interprets("(-2 m)^-2", 0.25, "m-2");
+    }
+
+    #[test]
+    fn it_reports_unbalanced_parentheses() {
+        assert_eq!(interpret("(1 m + 2 m").unwrap_err().span(), &(0..1));
+        assert_eq!(interpret("1 m + 2 m)").unwrap_err().span(), &(9..10));
+        assert!(matches!(interpret("1 m * ()"), Err(CalculumError::Syntax { .. })));
+    }
+
+    #[test]
+    fn it_spans_errors_in_groups() {
+        let err = interpret("2 m * (3 s + 1 m)").unwrap_err();
+
+        assert!(matches!(err, CalculumError::Incommensurable { .. }));
+        assert_eq!(err.span(), &(6..17));
+    }
+
+    #[test]
+    fn it_takes_roots_of_quantities() {
+        assert_interprets("sqrt(9 m2)", 3., "m");
+        assert_interprets("(8 m3)^(1/3)", 2., "m");
+        assert_interprets("(16 m4/s2)^0.5", 4., "m2.s-1");
+        assert_interprets("sqrt(1 [ft_i]2) -> cm", 30.48, "cm");
+        assert_interprets("sqrt(2) * sqrt(2)", 2., "");
+    }
+
+    #[test]
+    fn it_rejects_fractional_unit_exponents_by_default() {
+        assert_eq!(
+            interpret("sqrt(2 m)").unwrap_err(),
+            CalculumError::FractionalExponent { term: "m".to_string(), exponent: "0.5".to_string(), span: 0..9 }
+        );
+        assert!(matches!(interpret("(2 m)^(1/3)"), Err(CalculumError::FractionalExponent { .. })));
+        assert!(matches!(interpret("(2 m2)^3.14159"), Err(CalculumError::FractionalExponent { .. })));
+    }
+
+    #[test]
+    fn it_allows_fractional_unit_exponents_when_enabled() {
+        let mut env = Environment::new();
+        env.rational_exponents = true;
+
+        execute("noise = sqrt(4 m2/Hz) / 2", &mut env).unwrap();
+        assert_eq!(execute_quantity("noise", &mut env).term.to_string(), "Hz^(-1/2).m");
+
+        let qn = execute_quantity("noise^2 -> m2/Hz", &mut env);
+        assert_eq!(qn.mag, 1.);
+        let qn = execute_quantity("noise + sqrt(10000 cm2/Hz)", &mut env);
+        assert_eq!(qn.mag, 2.);
+        assert!(matches!(execute("noise + 1 m", &mut env), Err(CalculumError::Incommensurable { .. })));
+    }
+
+    #[test]
+    fn it_converts_rational_unit_exponents() {
+        assert_interprets("1 [m/s2/Hz^(1/2)] -> m/s2/Hz^(1/2)", 1., "m/s2/Hz^(1/2)");
+        assert_interprets("1 mV/Hz^(1/2) -> uV/kHz^(1/2)", 1e3 * 1e3f64.sqrt(), "uV/kHz^(1/2)");
+        assert_interprets("2 V/Hz^(1/2) * 3 Hz^(1/2)", 6., "V");
+        assert_interprets("(2 [m/s2/Hz^(1/2)])^2 -> m2/s4/Hz", 4., "m2/s4/Hz");
+        assert!(matches!(interpret("1 V/Hz^(1/2) -> V/Hz"), Err(CalculumError::Incommensurable { .. })));
+    }
+
+    #[test]
+    fn it_reports_unknown_functions() {
+        assert_eq!(
+            interpret("2 * foo(1 m)").unwrap_err(),
+            CalculumError::UnknownFunction { name: "foo".to_string(), span: 4..12 }
+        );
+        assert!(matches!(interpret("sqrt(1 m2, 2)"), Err(CalculumError::Syntax { span, .. }) if span == (0..13)));
+        assert!(matches!(interpret("sqrt(1 m2"), Err(CalculumError::Syntax { span, .. }) if span == (4..5)));
+    }
+
+    // -- Comparisons
+
+    fn compares(s: &str) -> Result<bool> {
+        super::interpret(s, &Environment::new())
+            .map(|value| value.as_bool().unwrap_or_else(|| panic!("{}: {:?} isn't a boolean", s, value)))
+    }
+
+    #[test]
+    fn it_reads_comparisons() {
+        use tokenizer::Token;
+
+        assert_eq!(tokens("1 m <= 2 m")[1], Token::Le);
+        assert_eq!(tokens("1 m != 2")[1], Token::Ne);
+        assert_eq!(tokens("1 km/h ~~ m/s"), vec![
+            quantity(1., "km/h"),
+            Token::Commensurable,
+            Token::Term(unit_term("m/s")),
+        ]);
+        assert!(matches!(interpret("1 m = 1 m"), Err(CalculumError::Syntax { span, .. }) if span == (4..5)));
+    }
+
+    #[test]
+    fn it_converts_before_comparing() {
+        assert_eq!(compares("1 km > 999 m"), Ok(true));
+        assert_eq!(compares("1 [ft_i] == 12 [in_i]"), Ok(true));
+        assert_eq!(compares("100 cm != 1 m"), Ok(false));
+        assert_eq!(compares("1 h <= 60 min"), Ok(true));
+        assert_eq!(compares("1 h < 60 min"), Ok(false));
+        assert_eq!(compares("37 Cel >= 98 [degF]"), Ok(true));
+        assert_eq!(compares("2 > 1"), Ok(true));
+    }
+
+    #[test]
+    fn it_compares_last() {
+        assert_eq!(compares("1 m + 1 m == 2 m"), Ok(true));
+        assert_eq!(compares("1 km -> m == 1000 m"), Ok(true));
+        assert_eq!(compares("(1 m < 2 m) == (2 s < 1 s)"), Ok(false));
+    }
+
+    #[test]
+    fn it_rejects_incommensurable_comparisons() {
+        assert!(matches!(
+            compares("1 m < 1 s"),
+            Err(CalculumError::Incommensurable { span, .. }) if span == (0..9)
+        ));
+        assert!(matches!(compares("1 [iU] == 1 [CFU]"), Err(CalculumError::ArbitraryUnit { .. })));
+    }
+
+    #[test]
+    fn it_checks_commensurability() {
+        assert_eq!(compares("1 km/h ~~ m/s"), Ok(true));
+        assert_eq!(compares("1 N ~~ J"), Ok(false));
+        assert_eq!(compares("3 m ~~ 2 s"), Ok(false));
+        assert_eq!(compares("1 Cel ~~ K"), Ok(true));
+        assert_eq!(compares("1 [iU] ~~ [CFU]"), Ok(false));
+    }
+
+    #[test]
+    fn it_reads_unit_terms_on_either_side_of_commensurable() {
+        assert_eq!(compares("m ~~ s"), Ok(false));
+        assert_eq!(compares("m/s ~~ km/h"), Ok(true));
+        assert_eq!(compares("N~~J"), Ok(false));
+        assert_eq!(compares("(m ~~ s) == (s ~~ m)"), Ok(true));
+        assert_eq!(compares("ABS(-1 m) ~~ km"), Ok(true));
+
+        let mut env = Environment::new();
+        execute("d = 2 km", &mut env).unwrap();
+        assert_eq!(execute("d ~~ h", &mut env), Ok(Some(Value::Bool(true))));
+        assert_eq!(execute("(d) ~~ h", &mut env), Ok(Some(Value::Bool(false))));
+    }
+
+    #[test]
+    fn it_keeps_booleans_out_of_arithmetic() {
+        assert_eq!(
+            super::interpret("(1 m < 2 m) + 1", &Environment::new()).unwrap_err(),
+            CalculumError::TypeMismatch { expected: "quantity".to_string(), found: "boolean".to_string(), span: 0..11 }
+        );
+        assert!(matches!(compares("(1 < 2) < 3"), Err(CalculumError::TypeMismatch { .. })));
+        assert!(matches!(compares("(1 < 2) ~~ 1"), Err(CalculumError::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn it_assigns_booleans() {
+        let mut env = Environment::new();
+        execute("far = 5 km > 3 [mi_i]", &mut env).unwrap();
+        execute("d = 2 km", &mut env).unwrap();
+
+        assert_eq!(execute("far", &mut env), Ok(Some(Value::Bool(true))));
+        assert_eq!(execute("(d) ~~ (d)", &mut env), Ok(Some(Value::Bool(true))));
+        assert_eq!(execute("far == (d < 1 m)", &mut env), Ok(Some(Value::Bool(false))));
+    }
+
+    // -- Functions
+
+    #[test]
+    fn it_aggregates_in_the_first_unit() {
+        assert_interprets("SUM(1 km, 500 m, 50000 cm)", 2., "km");
+        assert_interprets("AVERAGE(1 h, 30 min)", 0.75, "h");
+        assert_interprets("MIN(1 km, 900 m, 1 [mi_i])", 0.9, "km");
+        assert_interprets("MAX(1 km, 900 m, 1 [mi_i])", 1.609344, "km");
+        assert_interprets("sum(1, 2, 3)", 6., "");
+    }
+
+    #[test]
+    fn it_rejects_mixed_dimensions_in_aggregates() {
+        assert!(matches!(
+            interpret("SUM(1 m, 2 s)"),
+            Err(CalculumError::Incommensurable { span, .. }) if span == (9..12)
+        ));
+        assert!(matches!(interpret("MAX(1 m, 1 < 2)"), Err(CalculumError::TypeMismatch { .. })));
+        assert!(matches!(
+            interpret("AVERAGE()"),
+            Err(CalculumError::Syntax { message, .. }) if message.contains("at least 1")
+        ));
+    }
+
+    #[test]
+    fn it_rounds_quantities() {
+        assert_interprets("ABS(-3 m)", 3., "m");
+        assert_interprets("ROUND(2.5 m)", 3., "m");
+        assert_interprets("ROUND(2.71828 m, 2)", 2.72, "m");
+        assert_interprets("ROUND(1234 m, -2)", 1200., "m");
+        assert_interprets("ROUND(1 [mi_i], 2, km)", 1.61, "km");
+        assert_interprets("ROUND(100 km/h, 1, m/s)", 27.8, "m.s-1");
+        assert_interprets("ROUND(1 m, 1, [ft_i])", 3.3, "[ft_i]");
+        assert!(matches!(interpret("ROUND(1 m, 1, s)"), Err(CalculumError::Incommensurable { .. })));
+        assert!(matches!(interpret("ROUND(1 m, 1 m)"), Err(CalculumError::Incommensurable { .. })));
+    }
+
+    #[test]
+    fn it_floors_to_a_significance() {
+        assert_interprets("FLOOR(2.7 m)", 2., "m");
+        assert_interprets("CEILING(2.1 m)", 3., "m");
+        assert_interprets("FLOOR(1.37 km, 100 m)", 1.3, "km");
+        assert_interprets("CEILING(1.31 km, 100 m)", 1.4, "km");
+        assert_interprets("FLOOR(0.3, 0.1)", 0.3, "");
+        assert_eq!(interpret("FLOOR(0.3, 0.1)").unwrap().mag, 0.3);
+        assert_eq!(interpret("CEILING(0.61, 0.2)").unwrap().mag, 0.8);
+        assert_eq!(interpret("FLOOR(0.7 m, 10 cm)").unwrap().mag, 0.7);
+        assert_eq!(interpret("FLOOR(1 m, 1 m / 3)").unwrap().mag, 1.);
+        assert_eq!(interpret("FLOOR(1 m, 0 cm)").unwrap_err(), CalculumError::DivisionByZero { span: 11..15 });
+    }
+
+    #[test]
+    fn it_branches_with_if() {
+        assert_interprets("IF(1 km > 1 m, 1 m, 2 m)", 1., "m");
+        assert_interprets("IF(1 km < 1 m, 1 m, 2 s)", 2., "s");
+        // The branch not taken isn't evaluated
+        assert_interprets("IF(2 > 1, 1 m, 1 m + 1 s)", 1., "m");
+        assert_eq!(super::interpret("IF(2 < 1, 1 m)", &Environment::new()), Ok(Value::Bool(false)));
+        assert_eq!(
+            interpret("IF(1 m, 1 m, 2 m)").unwrap_err(),
+            CalculumError::TypeMismatch { expected: "boolean".to_string(), found: "quantity".to_string(), span: 3..6 }
+        );
+    }
+
+    #[test]
+    fn it_reads_bracketed_terms_as_quantities() {
+        assert_interprets("[pi] * 2", 2., "[pi]");
+        assert_interprets("50 * % -> 1", 0.5, "1");
+    }
+
+    #[test]
+    fn it_takes_angles_in_trig_functions() {
+        assert_interprets("sin(30 deg)", 0.5, "");
+        assert_interprets("sin(1800 ')", 0.5, "");
+        assert_interprets("sin(108000 '')", 0.5, "");
+        assert_interprets("sin(100 gon)", 1., "");
+        assert_interprets("cos(0.5 [pi].rad) + 1", 1., "");
+        assert_interprets("TAN(45 deg)", 1., "");
+        assert_interprets("sin(0.5)", 0.5f64.sin(), "");
+        assert!(matches!(
+            interpret("cos(1 m)"),
+            Err(CalculumError::Incommensurable { span, .. }) if span == (4..7)
+        ));
+    }
+
+    #[test]
+    fn it_requires_dimensionless_exponents_and_logarithms() {
+        assert_interprets("exp(2 s / 1 s)", 2f64.exp(), "");
+        assert_interprets("ln(1 m / 1 mm)", 1000f64.ln(), "");
+        assert_interprets("log10(1000)", 3., "");
+        assert_interprets("log10(1 %)", -2., "");
+        assert!(matches!(
+            interpret("ln(3 m)"),
+            Err(CalculumError::Incommensurable { span, .. }) if span == (3..6)
+        ));
+        assert!(matches!(interpret("exp(1 deg)"), Err(CalculumError::Incommensurable { .. })));
+        assert_eq!(
+            interpret("ln(0)").unwrap_err(),
+            CalculumError::OutOfDomain { function: "ln".to_string(), value: "0".to_string(), span: 3..4 }
+        );
+    }
+
+    // -- User-defined functions
+
+    #[test]
+    fn it_defines_functions_with_units() {
+        let mut env = Environment::new();
+        assert_eq!(execute("speed(d: m, t: s): m/s = d / t", &mut env), Ok(None));
+
+        let qn = execute_quantity("speed(36 km, 1 h)", &mut env);
+        assert!((qn.mag - 10.).abs() < 1e-9, "{:?}", qn);
+        assert_eq!(qn.term.to_string(), "m/s");
+        assert_eq!(env.function("speed").unwrap().to_string(), "(d: m, t: s): m/s");
+    }
+
+    #[test]
+    fn it_checks_arguments_against_the_parameters() {
+        let mut env = Environment::new();
+        execute("speed(d: m, t: s): m/s = d / t", &mut env).unwrap();
+
+        assert!(matches!(
+            execute("speed(1 m, 1 kg)", &mut env),
+            Err(CalculumError::Incommensurable { span, .. }) if span == (11..15)
+        ));
+        assert!(matches!(
+            execute("speed(1 m, 1 < 2)", &mut env),
+            Err(CalculumError::TypeMismatch { span, .. }) if span == (11..16)
+        ));
+        assert!(matches!(execute("speed(1 m)", &mut env), Err(CalculumError::Syntax { .. })));
+    }
+
+    #[test]
+    fn it_checks_the_result_against_the_unit() {
+        let mut env = Environment::new();
+        execute("area(l: m, w: m): m3 = l * w", &mut env).unwrap();
+
+        assert!(matches!(
+            execute("2 * area(1 m, 2 m)", &mut env),
+            Err(CalculumError::Incommensurable { span, .. }) if span == (4..18)
+        ));
+    }
+
+    #[test]
+    fn it_evaluates_bodies_in_their_own_scope() {
+        let mut env = Environment::new();
+        execute("x = 1 s", &mut env).unwrap();
+        execute("g = 10 m/s2", &mut env).unwrap();
+        execute("fall(x: s) = g * x^2 / 2", &mut env).unwrap();
+        execute("twice(x) = IF(x > 0 m, 2 * x, x)", &mut env).unwrap();
+
+        assert_eq!(execute_quantity("fall(2 s) -> m", &mut env).mag, 20.);
+        assert_eq!(execute_quantity("x", &mut env).mag, 1.);
+        assert_eq!(execute_quantity("twice(3 m)", &mut env).mag, 6.);
+        assert_eq!(execute("f() = 2 m", &mut env).and_then(|_| execute("f() == 200 cm", &mut env)), Ok(Some(Value::Bool(true))));
+    }
+
+    #[test]
+    fn it_doesnt_recurse() {
+        let mut env = Environment::new();
+        execute("f(x) = f(x)", &mut env).unwrap();
+
+        assert!(matches!(execute("f(1)", &mut env), Err(CalculumError::UnknownFunction { span, .. }) if span == (0..4)));
+    }
+
+    #[test]
+    fn it_rejects_invalid_function_definitions() {
+        let mut env = Environment::new();
+
+        assert_eq!(
+            execute("sum(x) = x", &mut env),
+            Err(CalculumError::AlreadyDefined { name: "sum".to_string(), span: 0..3 })
+        );
+        assert!(matches!(execute("f(x, x) = x", &mut env), Err(CalculumError::Syntax { span, .. }) if span == (5..6)));
+        assert!(matches!(execute("f(2 m) = 1", &mut env), Err(CalculumError::Syntax { span, .. }) if span == (2..5)));
+        assert!(matches!(execute("f(x:) = x", &mut env), Err(CalculumError::Syntax { span, .. }) if span == (4..4)));
+        assert!(matches!(
+            execute("f(x: [foo]) = x", &mut env),
+            Err(CalculumError::UnknownAtom { span, .. }) if span == (5..10)
+        ));
+        assert!(matches!(execute("f(x): m = x +", &mut env), Err(CalculumError::Syntax { span, .. }) if span == (13..13)));
+        assert_eq!(env.function("f"), None);
+    }
+
+    fn check_types(s: &str) -> Type {
+        Checker::new(&Environment::new()).statement(s).unwrap().unwrap()
+    }
+
+    fn dimension(t: &str) -> Type {
+        Type::Quantity(Some(Dimension::of(&unit_term(t))))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn it_infers_dimensions() {
+        assert_eq!(check_types("3 km / 2 h"), dimension("m/s"));
+        assert_eq!(check_types("-(2 m)^2 * 3"), dimension("m2"));
+        assert_eq!(check_types("sqrt(4 m2) -> [ft_i]"), dimension("m"));
+        assert_eq!(check_types("(8 m3)^(1/3)"), dimension("m"));
+        assert_eq!(check_types("SUM(1 km, 2 m) > 3 [mi_i]"), Type::Bool);
+        assert_eq!(check_types("sin(30 deg) + exp(2)"), dimension("1"));
+        assert_eq!(check_types("IF(1 < 2, 1 m, 2 s)"), Type::Quantity(None));
+    }
+
+    #[test]
+    fn it_checks_without_evaluating() {
+        let errors = check("x = 1 / 0 m\ny = x + 2 s\nz = y * 2", &Environment::new());
+
+        assert_eq!(errors, vec![CalculumError::Incommensurable {
+            lhs: "L-1".to_string(),
+            rhs: "T".to_string(),
+            span: 16..23
+        }]);
+    }
+
+    #[test]
+    fn it_reports_every_inconsistency() {
+        let errors = check("1 m + 1 s\n2 kg -> m\nsin(2 m) == ln(1 kg)\nIF(1 m, 2, 3)", &Environment::new());
+        let spans: Vec<Span> = errors.iter().map(|e| e.span().clone()).collect();
+
+        assert_eq!(spans, vec![0..9, 10..19, 24..27, 35..39, 44..47]);
+        assert!(matches!(errors[4], CalculumError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn it_checks_units_beyond_dimension_vectors() {
+        let script = "sqrt(2 m)\n\
+                      1 Cel * 2 Cel\n\
+                      1 [iU] + 1 g\n\
+                      dimension [currency]\n\
+                      1 [currency] + 1 m\n\
+                      2 * 37 Cel + 1 K\n\
+                      sqrt(4 m2) * (1 [currency]/m)^2 -> [currency]2/m";
+        let errors = check(script, &Environment::new());
+
+        assert_eq!(errors, vec![
+            CalculumError::FractionalExponent { term: "L".to_string(), exponent: "0.5".to_string(), span: 0..9 },
+            CalculumError::SpecialUnit { unit: "Cel".to_string(), span: 10..23 },
+            CalculumError::ArbitraryUnit { unit: "[iU]".to_string(), other: "M".to_string(), span: 24..36 },
+            CalculumError::Incommensurable { lhs: "[currency]".to_string(), rhs: "L".to_string(), span: 58..76 },
+            CalculumError::SpecialUnitAddition { unit: "Cel".to_string(), span: 77..93 },
+        ]);
+    }
+
+    #[test]
+    fn it_checks_names() {
+        let mut env = Environment::new();
+        execute("t = 2 s", &mut env).unwrap();
+        let errors = check("define [pace] = 1 s/m\nd = 3 km\nd * [pace] + t + v\nf(d)", &env);
+
+        assert_eq!(errors, vec![
+            CalculumError::UnknownVariable { name: "v".to_string(), span: 48..49 },
+            CalculumError::UnknownFunction { name: "f".to_string(), span: 50..54 },
+        ]);
+    }
+
+    #[test]
+    fn it_checks_function_signatures() {
+        let script = "speed(d: m, t: s): m/s = d / t\n\
+                      speed(1 km, 2 kg) -> [mi_i]/h\n\
+                      wrong(x: m): s = 2 * x\n\
+                      double(x) = 2 * x\n\
+                      double(1 m) + double(1 s)\n\
+                      f(x) = f(x)";
+        let errors = check(script, &Environment::new());
+        let spans: Vec<Span> = errors.iter().map(|e| e.span().clone()).collect();
+
+        assert_eq!(spans, vec![43..47, 78..83, 102..127, 135..139]);
+        assert!(matches!(errors[3], CalculumError::UnknownFunction { .. }));
+    }
+
+    #[test]
+    fn it_checks_names_in_function_bodies_at_calls() {
+        let script = "f(x) = x * k + g(x)\n\
+                      g(x) = 2 * x\n\
+                      f(3)\n\
+                      k = 2\n\
+                      f(3)\n\
+                      h(x: m): m = x + j\n\
+                      j = 1 s\n\
+                      h(1 m)";
+        let errors = check(script, &Environment::new());
+
+        assert_eq!(errors, vec![
+            CalculumError::UnknownVariable { name: "k".to_string(), span: 33..37 },
+            CalculumError::Incommensurable { lhs: "L".to_string(), rhs: "T".to_string(), span: 76..82 },
+        ]);
+    }
+
+    #[test]
+    fn it_infers_the_types_of_calls() {
+        let mut checker = Checker::new(&Environment::new());
+        checker.statement("speed(d: m, t: s): km/h = d / t").unwrap();
+        checker.statement("half(x) = x / 2").unwrap();
+
+        assert_eq!(checker.statement("speed(1 m, 1 s)"), Ok(Some(dimension("m/s"))));
+        assert_eq!(checker.statement("half(1 m2)"), Ok(Some(dimension("m2"))));
+        assert_eq!(checker.statement("half(1 > 2)"), Err(vec![CalculumError::TypeMismatch {
+            expected: "quantity".to_string(),
+            found: "boolean".to_string(),
+            span: 0..11
+        }]));
+    }
+}
@@ -0,0 +1,277 @@
+//! # Unit Registry
+//! The prefixes & unit atoms a term can use: UCUM's own (from
+//! `ucum::essence`), plus any defined at runtime, e.g., `[pallet]` as
+//! `40 [ft_i]3`, or a `Ki`-style prefix of your own.
+//!
+//! Definitions only ever add to the built-in tables: redefining an existing
+//! atom or prefix is an error, so `m` always means meter.
+//!
+//! New base units (e.g., `[currency]`) stand for a dimension of their own,
+//! so they're incommensurable with everything but the units defined in
+//! terms of them.
+//!
+use crate::ucum::error::{CalculumError, Result};
+use crate::ucum::essence::{essence, BaseUnit, Prefix, UnitDef, Value};
+use crate::ucum::term::UnitTerm;
+use crate::ucum::unit;
+
+#[derive(Debug, Clone, Default)]
+pub struct UnitRegistry {
+    prefixes: Vec<Prefix>,
+    base_units: Vec<BaseUnit>,
+    units: Vec<UnitDef>,
+}
+
+/// Whether `code` reads as a single atom in a unit term: anything goes
+/// within square brackets, but outside them, operators, digits & signs
+/// (which would be read as an exponent) aren't allowed.
+fn is_atom_code(code: &str) -> bool {
+    let mut depth = 0;
+
+    for c in code.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 0 => return false,
+            ']' => depth -= 1,
+            _ if depth > 0 => {},
+            '.' | '*' | '/' | '^' | '(' | ')' | '{' | '}' | '+' | '-' | '0'..='9' => return false,
+            _ if c.is_whitespace() => return false,
+            _ => {},
+        }
+    }
+    depth == 0 && !code.is_empty()
+}
+
+impl UnitRegistry {
+    /// Just the built-in units & prefixes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a prefix, e.g., `("Mx", 1e6)`, unless it'd give an existing
+    /// atom a second reading (`ca` on `l` would read as `cal`).
+    pub fn define_prefix(&mut self, code: &str, factor: f64) -> Result<()> {
+        if code.is_empty() || self.prefix(code).is_some() {
+            return Err(CalculumError::AlreadyDefined { name: code.to_string(), span: 0..0 })
+        }
+        let metric_atoms = self.base_units().map(|u| &u.code)
+            .chain(self.units().filter(|u| u.is_metric).map(|u| &u.code));
+        for atom in metric_atoms {
+            let prefixed = format!("{}{}", code, atom);
+            if self.is_atom(&prefixed) || unit::prefixed_atom(&prefixed, self).is_some() {
+                return Err(CalculumError::AmbiguousPrefix {
+                    prefix: code.to_string(),
+                    unit: prefixed,
+                    span: 0..0
+                })
+            }
+        }
+
+        self.prefixes.push(Prefix {
+            code: code.to_string(),
+            name: code.to_string(),
+            print_symbol: None,
+            factor,
+        });
+        Ok(())
+    }
+
+    fn check_atom_code(&self, code: &str) -> Result<()> {
+        if !is_atom_code(code) {
+            return Err(CalculumError::syntax(format!("'{}' isn't a valid unit atom.", code), 0..0))
+        }
+        // `km` is already a kilo-meter
+        if self.is_atom(code) || unit::prefixed_atom(code, self).is_some() {
+            return Err(CalculumError::AlreadyDefined { name: code.to_string(), span: 0..0 })
+        }
+        Ok(())
+    }
+
+    /// Adds a base unit for a new dimension, e.g., `[currency]`. Like UCUM's
+    /// base units, it's metric.
+    pub fn define_base_unit(&mut self, code: &str) -> Result<()> {
+        self.check_atom_code(code)?;
+
+        let name = code.trim_start_matches('[').trim_end_matches(']');
+        self.base_units.push(BaseUnit {
+            code: code.to_string(),
+            name: name.to_string(),
+            print_symbol: None,
+            property: name.to_string(),
+            dim: name.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Adds an atom worth `factor` times the unit term `term`, e.g.,
+    /// `("[pallet]", 40., "[ft_i]3", false)`. Only metric atoms take prefixes.
+    pub fn define_unit(&mut self, code: &str, factor: f64, term: &str, is_metric: bool) -> Result<()> {
+        self.check_atom_code(code)?;
+
+        // Special units can't be scaled, so they can't define other units
+        if UnitTerm::new(term, self)?.special().is_some() {
+            return Err(CalculumError::SpecialUnitDefinition { unit: term.to_string(), span: 0..term.len() })
+        }
+
+        self.units.push(UnitDef {
+            code: code.to_string(),
+            name: code.to_string(),
+            print_symbol: None,
+            property: "".to_string(),
+            class: "user".to_string(),
+            is_metric,
+            is_special: false,
+            is_arbitrary: false,
+            value: Value { unit: term.to_string(), factor, function: None },
+        });
+        Ok(())
+    }
+
+    pub fn prefix(&self, code: &str) -> Option<&Prefix> {
+        essence().prefix(code).or_else(|| self.prefixes.iter().find(|p| p.code == code))
+    }
+
+    pub fn base_unit(&self, code: &str) -> Option<&BaseUnit> {
+        essence().base_unit(code).or_else(|| self.base_units.iter().find(|u| u.code == code))
+    }
+
+    pub fn unit(&self, code: &str) -> Option<&UnitDef> {
+        essence().unit(code).or_else(|| self.units.iter().find(|u| u.code == code))
+    }
+
+    pub fn is_atom(&self, code: &str) -> bool {
+        self.base_unit(code).is_some() || self.unit(code).is_some()
+    }
+
+    /// Base units are all metric.
+    pub fn is_metric(&self, code: &str) -> bool {
+        self.base_unit(code).is_some() || self.unit(code).is_some_and(|u| u.is_metric)
+    }
+
+    /// UCUM's base units, then any defined at runtime.
+    pub fn base_units(&self) -> impl Iterator<Item = &BaseUnit> {
+        essence().base_units.iter().chain(&self.base_units)
+    }
+
+    /// UCUM's other units, then any defined at runtime.
+    pub fn units(&self) -> impl Iterator<Item = &UnitDef> {
+        essence().units.iter().chain(&self.units)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_starts_from_the_built_in_units() {
+        let registry = UnitRegistry::new();
+
+        assert!(registry.is_atom("[ft_i]"));
+        assert!(registry.is_metric("m"));
+        assert_eq!(registry.prefix("k").map(|p| p.factor), Some(1e3));
+        assert!(!registry.is_atom("[pallet]"));
+    }
+
+    #[test]
+    fn it_defines_units_and_prefixes() {
+        let mut registry = UnitRegistry::new();
+        registry.define_unit("[pallet]", 40., "[ft_i]3", false).unwrap();
+        registry.define_prefix("Q", 1e30).unwrap();
+
+        assert_eq!(registry.unit("[pallet]").unwrap().value.unit, "[ft_i]3");
+        assert!(!registry.is_metric("[pallet]"));
+        assert_eq!(registry.prefix("Q").map(|p| p.factor), Some(1e30));
+    }
+
+    #[test]
+    fn it_lists_built_in_and_defined_units() {
+        let mut registry = UnitRegistry::new();
+        registry.define_unit("[pallet]", 40., "[ft_i]3", false).unwrap();
+        registry.define_base_unit("[currency]").unwrap();
+
+        assert_eq!(registry.base_units().next().map(|u| u.code.as_str()), Some("m"));
+        assert_eq!(registry.base_units().last().map(|u| u.code.as_str()), Some("[currency]"));
+        assert!(registry.units().any(|u| u.code == "[ft_i]"));
+        assert_eq!(registry.units().last().map(|u| u.code.as_str()), Some("[pallet]"));
+    }
+
+    #[test]
+    fn it_rejects_redefinitions() {
+        let mut registry = UnitRegistry::new();
+        registry.define_unit("[pallet]", 40., "[ft_i]3", false).unwrap();
+
+        assert_eq!(
+            registry.define_unit("m", 1., "[ft_i]", true),
+            Err(CalculumError::AlreadyDefined { name: "m".to_string(), span: 0..0 })
+        );
+        assert!(registry.define_unit("[pallet]", 1., "m3", false).is_err());
+        assert!(registry.define_prefix("k", 1e3).is_err());
+        assert!(registry.define_base_unit("[pallet]").is_err());
+    }
+
+    #[test]
+    fn it_rejects_atoms_that_read_as_prefixed_atoms() {
+        let mut registry = UnitRegistry::new();
+
+        assert_eq!(
+            registry.define_unit("km", 5., "m", false),
+            Err(CalculumError::AlreadyDefined { name: "km".to_string(), span: 0..0 })
+        );
+        assert!(registry.define_unit("mm", 5., "s", false).is_err());
+        assert!(registry.define_base_unit("ks").is_err());
+        // `[in_i]` doesn't take prefixes, so `k[in_i]` is free
+        assert!(registry.define_unit("kin", 1., "[in_i]", false).is_ok());
+    }
+
+    #[test]
+    fn it_rejects_prefixes_that_make_atoms_ambiguous() {
+        let mut registry = UnitRegistry::new();
+
+        assert_eq!(
+            registry.define_prefix("ca", 1e2),
+            Err(CalculumError::AmbiguousPrefix { prefix: "ca".to_string(), unit: "cal".to_string(), span: 0..0 })
+        );
+        registry.define_unit("wm", 1., "m", true).unwrap();
+        // `kwm` would be a kilo-`wm` or a `kw`-meter
+        assert!(matches!(registry.define_prefix("kw", 1e2), Err(CalculumError::AmbiguousPrefix { .. })));
+        assert!(registry.define_prefix("Q", 1e30).is_ok());
+    }
+
+    #[test]
+    fn it_defines_base_units() {
+        let mut registry = UnitRegistry::new();
+        registry.define_base_unit("[currency]").unwrap();
+
+        assert_eq!(registry.base_unit("[currency]").unwrap().dim, "currency");
+        assert!(registry.is_metric("[currency]"));
+    }
+
+    #[test]
+    fn it_only_defines_atoms_that_can_be_read_back() {
+        let mut registry = UnitRegistry::new();
+
+        for code in ["", "m/s", "foo2", "a b", "[foo", "{x}"] {
+            assert!(
+                matches!(registry.define_unit(code, 1., "m", true), Err(CalculumError::Syntax { .. })),
+                "{}", code
+            );
+        }
+        assert!(registry.define_unit("[m/s2]", 1., "m/s2", false).is_ok());
+    }
+
+    #[test]
+    fn it_rejects_invalid_definitions() {
+        let mut registry = UnitRegistry::new();
+
+        assert!(matches!(
+            registry.define_unit("[foo]", 1., "[bar]", false),
+            Err(CalculumError::UnknownAtom { .. })
+        ));
+        assert!(matches!(
+            registry.define_unit("[warm]", 1., "Cel", false).map_err(|e| e.message()),
+            Err(message) if message == "[Unit Error] The special unit 'Cel' can't define another unit."
+        ));
+    }
+}
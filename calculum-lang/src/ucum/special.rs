@@ -0,0 +1,192 @@
+//! # Special Units
+//! Most units are ratio units: `2 m` is twice `1 m`, and converting between
+//! them is a matter of multiplying by a factor. Special units measure on
+//! some other scale over a ratio unit instead, e.g.:
+//! - `Cel` is offset from `K` (`0 Cel` is `273.15 K`),
+//! - `[pH]` is the negative logarithm of a concentration in `mol/l`, &
+//! - `B[SPL]` is twice the logarithm of a pressure over `2.10*-5.Pa`.
+//!
+//! Each one has a forward function, from the ratio unit to the special
+//! unit, and its inverse. The essence names the function & the ratio unit
+//! it works over (e.g., `degF` over `5 K/9` for `[degF]`). Since `2 Cel`
+//! isn't twice `1 Cel`, special units can't be added, multiplied, divided
+//! or raised to powers.
+//!
+//! UCUM also marks `[m/s2/Hz^(1/2)]` as special, only because its
+//! exponents are integers. It's the square root of `m2/s4/Hz`, so, with
+//! fractional exponents, it's a ratio unit like any other (see `root_of`).
+//!
+use crate::ucum::error::Result;
+use crate::ucum::essence::{essence, FunctionDef, UnitDef};
+use crate::ucum::registry::UnitRegistry;
+use crate::ucum::term::UnitTerm;
+use crate::ucum::unit;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    Cel,
+    DegF,
+    DegRe,
+    PH,
+    Ln,
+    Lg,
+    TwoLg,
+    HundredTan,
+    HpX,
+    HpC,
+    HpM,
+    HpQ,
+    Ld,
+}
+
+impl Function {
+    /// The function's name in the essence (`lgTimes2` for `B[SPL]`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        use Function::*;
+
+        match name {
+            "Cel" => Some(Cel),
+            "degF" => Some(DegF),
+            "degRe" => Some(DegRe),
+            "pH" => Some(PH),
+            "ln" => Some(Ln),
+            "lg" => Some(Lg),
+            "lgTimes2" => Some(TwoLg),
+            "100tan" | "tanTimes100" => Some(HundredTan),
+            "hpX" => Some(HpX),
+            "hpC" => Some(HpC),
+            "hpM" => Some(HpM),
+            "hpQ" => Some(HpQ),
+            "ld" => Some(Ld),
+            _ => None,
+        }
+    }
+
+    /// From (a multiple of) the ratio unit to the special unit.
+    pub fn forward(&self, x: f64) -> f64 {
+        use Function::*;
+
+        match self {
+            Cel => x - 273.15,
+            DegF => x - 459.67,
+            DegRe => x - 218.52,
+            PH | HpX => -x.log10(),
+            Ln => x.ln(),
+            Lg => x.log10(),
+            TwoLg => 2. * x.log10(),
+            HundredTan => 100. * x.tan(),
+            HpC => -x.ln() / 100f64.ln(),
+            HpM => -x.ln() / 1_000f64.ln(),
+            HpQ => -x.ln() / 50_000f64.ln(),
+            Ld => x.log2(),
+        }
+    }
+
+    /// From the special unit to (a multiple of) the ratio unit.
+    pub fn inverse(&self, x: f64) -> f64 {
+        use Function::*;
+
+        match self {
+            Cel => x + 273.15,
+            DegF => x + 459.67,
+            DegRe => x + 218.52,
+            PH | HpX => 10f64.powf(-x),
+            Ln => x.exp(),
+            Lg => 10f64.powf(x),
+            TwoLg => 10f64.powf(x / 2.),
+            HundredTan => (x / 100.).atan(),
+            HpC => 100f64.powf(-x),
+            HpM => 1_000f64.powf(-x),
+            HpQ => 50_000f64.powf(-x),
+            Ld => 2f64.powf(x),
+        }
+    }
+}
+
+pub fn is_special(atom: &str) -> bool {
+    essence().unit(atom).is_some_and(|def| def.is_special && root_of(def).is_none())
+}
+
+/// The function of a unit defined as a square root, e.g., `sqrt` of
+/// `m2/s4/Hz` for `[m/s2/Hz^(1/2)]`.
+pub fn root_of(def: &UnitDef) -> Option<&FunctionDef> {
+    def.value.function.as_ref().filter(|function| def.is_special && function.name == "sqrt")
+}
+
+/// A (possibly prefixed) special unit, e.g., `dB` or `[degF]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecialUnit {
+    function: Function,
+    factor: f64,
+    ratio_unit: &'static str,
+    prefix: f64,
+}
+
+impl SpecialUnit {
+    pub fn find(u: &unit::Unit, registry: &UnitRegistry) -> Option<Self> {
+        let def = essence().unit(&u.atom)?.value.function.as_ref()?;
+
+        Some(SpecialUnit {
+            function: Function::from_name(&def.name)?,
+            factor: def.value,
+            ratio_unit: &def.unit,
+            prefix: u.prefix_factor(registry),
+        })
+    }
+
+    /// The ratio unit the special unit measures, e.g., `K` for `Cel`.
+    /// These are all UCUM's own, so the built-in units suffice.
+    pub fn ratio_term(&self) -> Result<UnitTerm> {
+        UnitTerm::new(self.ratio_unit, &UnitRegistry::default())
+    }
+
+    /// `37 Cel` is `310.15 K`; `20 dB` is `100` (times `1`).
+    pub fn to_ratio(&self, mag: f64) -> f64 {
+        self.function.inverse(mag * self.prefix) * self.factor
+    }
+
+    pub fn from_ratio(&self, mag: f64) -> f64 {
+        self.function.forward(mag / self.factor) / self.prefix
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use calculum_core::rational::Rational;
+
+    use super::*;
+
+    fn special_units() -> impl Iterator<Item = (&'static str, SpecialUnit)> {
+        let registry = UnitRegistry::default();
+        essence().units.iter()
+            .filter(|def| is_special(&def.code))
+            .map(move |def| (def.code.as_str(), SpecialUnit::find(&unit::Unit::new(def.code.clone(), Rational::ONE, None, &registry), &registry)
+                .unwrap_or_else(|| panic!("{} has no known function", def.code))))
+    }
+
+    #[test]
+    fn it_inverts_every_function() {
+        for (atom, special) in special_units() {
+            let x = special.from_ratio(special.to_ratio(0.5));
+
+            assert!((x - 0.5).abs() < 1e-12, "{}: {}", atom, x);
+        }
+    }
+
+    #[test]
+    fn it_parses_every_ratio_unit() {
+        for (atom, special) in special_units() {
+            assert!(special.ratio_term().is_ok(), "{} over {}", atom, special.ratio_unit);
+        }
+    }
+
+    #[test]
+    fn it_applies_prefixes_to_the_special_scale() {
+        let registry = UnitRegistry::default();
+        let db = SpecialUnit::find(&unit::Unit::new("dB".to_string(), Rational::ONE, None, &registry), &registry).unwrap();
+
+        assert!((db.to_ratio(20.) - 100.).abs() < 1e-12);
+        assert!((db.from_ratio(100.) - 20.).abs() < 1e-12);
+    }
+}
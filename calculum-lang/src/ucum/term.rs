@@ -1,209 +1,404 @@
 //! # Unit Terms
-//! - [ ] Support for parentheticals `(...)`. (Currently interpreted
-//!   as part of a symbol)
-//! - [ ] Support for exponents `10^`
+//! A unit term is a product of units, factors, and parenthesized sub-terms,
+//! e.g., `kg.m/s2`, `kg/(m.s2)`, `10^3.kg`, `[pi]/4.[mil_i]2`, or `/[in_i]`.
 //!
-use std::ops;
-use std::slice::Iter;
-use std::fmt::{self, Display};
+//! - Factors are integers (`4`) or powers of ten (`10^3`, `10*-7`).
+//! - Besides UCUM's integer exponents (`s-2`), a unit can be raised with
+//!   `^`, to an integer or a fraction in parentheses (`m^2`, `Hz^(-1/2)`).
+//! - A leading `/` is a unary division (`/s` is `1/s`).
+//! - Division binds to the component that immediately follows it, so
+//!   `/[pi].A/m` is `A/([pi].m)`.
+//!
+use std::fmt;
+
+use calculum_core::constants::{DimensionVector, MetricBaseUnitAtom};
+use calculum_core::rational::Rational;
 
+use crate::ucum::error::{CalculumError, Result};
+use crate::ucum::registry::UnitRegistry;
+use crate::ucum::special::{self, SpecialUnit};
 use crate::ucum::unit;
 
 
 pub mod tokenizer {
     use std::iter::Iterator;
-    use std::collections::VecDeque;
 
+    use calculum_core::rational::Rational;
+
+    use crate::ucum::error::{CalculumError, Result, Span};
+    use crate::ucum::registry::UnitRegistry;
     use crate::ucum::unit;
 
     #[derive(Debug, PartialEq)]
     pub enum Token {
         Mul,
         Div,
+        LParen,
+        RParen,
         Unit(unit::Unit),
-        Int(i8),
+        Int(i32),
+        /// `10^n` (or `10*n`): ten to the power `n`.
+        TenPow(i8),
     }
 
     #[derive(Debug, )]
     pub struct Tokenizer<'a> {
-        // Required in case we need to backtrack `10.1` -> `10.1` but `10.a` -> `10`, `.`, `a`
-        visited: VecDeque<char>,
-        chars: std::str::Chars<'a>,
+        s: &'a str,
+        // Byte offset of the next character. Needed for the spans in errors,
+        // and to look ahead (`10*3` -> `10*3` but `10*m` -> `10`, `*`, `m`).
+        pos: usize,
+        registry: &'a UnitRegistry,
     }
 
     impl<'a> Tokenizer<'a> {
-        pub fn new(s: &'a str) -> Self {
+        pub fn new(s: &'a str, registry: &'a UnitRegistry) -> Self {
             Tokenizer {
-                visited: VecDeque::new(),
-                chars: s.chars(),
+                s,
+                pos: 0,
+                registry,
             }
         }
+
+        fn peek(&self) -> Option<char> {
+            self.s[self.pos..].chars().next()
+        }
+
+        fn peek_second(&self) -> Option<char> {
+            self.s[self.pos..].chars().nth(1)
+        }
+
+        fn bump(&mut self) -> Option<char> {
+            let c = self.peek()?;
+            self.pos += c.len_utf8();
+            Some(c)
+        }
+
+        fn bump_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+            let start = self.pos;
+            while self.peek().is_some_and(&f) {
+                self.bump();
+            }
+            &self.s[start..self.pos]
+        }
     }
 
     trait Tokenizes {
         type Item;
-        fn read_annotation(&mut self) -> Option<String>;
-        fn read_int(&mut self, _: char) -> Option<Self::Item>;
-        fn read_unit(&mut self, _: char) -> Option<Self::Item>;
+        fn read_annotation(&mut self) -> Result<String>;
+        fn read_bracket(&mut self, _: &mut String) -> Result<()>;
+        fn read_exponent(&mut self) -> Result<i8>;
+        fn read_power(&mut self) -> Result<Rational>;
+        fn read_int(&mut self) -> Result<Self::Item>;
+        fn read_unit(&mut self) -> Result<Self::Item>;
     }
 
     impl<'a> Tokenizes for Tokenizer<'a> {
         type Item = Token;
 
-        fn read_annotation(&mut self) -> Option<String> {
-            let mut subs = String::from("");
-            let mut maybe_c = self.chars.next();
-
-            loop {
-                match maybe_c {
-                    None | Some('}') => break,
-                    Some(c) => subs.push(c),
-                };
+        /// Reads `{...}`, starting at the `{`.
+        fn read_annotation(&mut self) -> Result<String> {
+            let start = self.pos;
+            self.bump();
+            let subs = self.bump_while(|c| c != '}').to_string();
+
+            match self.bump() {
+                Some('}') => Ok(subs),
+                _ => Err(CalculumError::syntax(
+                    "'{' encountered without matching closing brace.", start..self.pos
+                ))
+            }
+        }
 
-                maybe_c = self.chars.next();
+        /// Square brackets are part of the atom (`[in_i]`, `m[H2O]`,
+        /// `[m/s2/Hz^(1/2)]`), so everything up to the matching `]` is
+        /// read verbatim.
+        fn read_bracket(&mut self, subs: &mut String) -> Result<()> {
+            let start = self.pos;
+            let mut depth = 0;
+
+            while let Some(c) = self.bump() {
+                subs.push(c);
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    return Ok(())
+                }
             }
 
-            maybe_c.map(|_| subs)
+            Err(CalculumError::syntax(
+                "'[' encountered without matching closing bracket.", start..self.pos
+            ))
         }
-        
-        fn read_int(&mut self, first_digit: char) -> Option<Self::Item> {
-            let mut subs = String::from("");
-            subs.push(first_digit);
 
-            let mut maybe_c = self.chars.next();
+        fn read_exponent(&mut self) -> Result<i8> {
+            let start = self.pos;
+            if let Some('+' | '-') = self.peek() {
+                self.bump();
+            }
+            self.bump_while(|c| c.is_ascii_digit());
+
+            let exponent = &self.s[start..self.pos];
+            exponent.parse::<i8>().map_err(|_| match exponent {
+                "" => CalculumError::syntax("Expected an exponent.", start..start),
+                "+" | "-" => CalculumError::syntax(
+                    format!("Expected digits after the sign '{}'.", exponent), start..self.pos
+                ),
+                _ => CalculumError::ExponentOverflow {
+                    exponent: exponent.to_string(),
+                    span: start..self.pos
+                }
+            })
+        }
 
-            loop {
-                match maybe_c {
-                    Some(c) => match c {
-                        '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => subs.push(c),
-                        _ => {
-                            self.visited.push_back(c);
-                            break
-                        }
-                    },
-                    None => break,
-                };
+        /// Reads what follows a unit's `^`: an integer (`2`, `-1`) or a
+        /// fraction in parentheses (`(1/2)`, `(-3/2)`).
+        fn read_power(&mut self) -> Result<Rational> {
+            let start = self.pos;
+            if self.peek() != Some('(') {
+                return self.read_exponent().map(Rational::from)
+            }
+            self.bump();
+
+            let numer = self.read_exponent()?;
+            let denom = match self.peek() {
+                Some('/') => {
+                    self.bump();
+                    self.read_exponent()?
+                },
+                _ => 1,
+            };
+
+            match self.bump() {
+                Some(')') if denom == 0 => Err(CalculumError::DivisionByZero { span: start..self.pos }),
+                Some(')') => Ok(Rational::new(numer.into(), denom.into())),
+                _ => Err(CalculumError::syntax(
+                    "'(' encountered without matching closing parenthesis.", start..start + 1
+                )),
+            }
+        }
 
-                maybe_c = self.chars.next();
+        fn read_int(&mut self) -> Result<Self::Item> {
+            let start = self.pos;
+            if let Some('+' | '-') = self.peek() {
+                self.bump();
+            }
+            let digits = self.bump_while(|c| c.is_ascii_digit());
+
+            // `10^n` & `10*n` are powers of ten, but `10*m` is `10.m`.
+            if digits == "10" && matches!(self.peek(), Some('^' | '*'))
+                && matches!(self.peek_second(), Some('+' | '-' | '0'..='9'))
+            {
+                self.bump();
+                return self.read_exponent().map(Token::TenPow)
             }
 
-            subs.parse::<i8>().ok().map(|int| Token::Int(int))
+            self.s[start..self.pos].parse::<i32>().map(Token::Int).map_err(|_| {
+                CalculumError::syntax(
+                    format!("The factor '{}' is out of range.", &self.s[start..self.pos]),
+                    start..self.pos
+                )
+            })
         }
 
-        fn read_unit(&mut self, first_char: char) -> Option<Self::Item> {
+        fn read_unit(&mut self) -> Result<Self::Item> {
+            let start = self.pos;
             let mut subs = String::from("");
-            let mut exp: i8 = 1;
+            let mut exp = Rational::ONE;
             let mut annotation: Option<String> = None;
-            let mut maybe_c = Some(first_char);
 
-            loop {
-                match maybe_c {
-                    Some(c) => match c {
-                        '.' | '*' | '/' | '^'   => {
-                            self.visited.push_back(c);
-                            break
-                        },
-                        '{' => {
-                            annotation = self.read_annotation();
-                            break
-                        },
-                        '+' | '-' | '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                            // Exponentiation doesn't accept terms
-                            exp = match self.read_int(c).unwrap() {
-                                Token::Int(i) => i.try_into().unwrap(),
-                                _ => {panic!("read_int should have returned an int here!")}
-                            };
-                            break;
-                        },
-                        _ => {
-                            subs.push(c);
-                        }
+            while let Some(c) = self.peek() {
+                match c {
+                    '^' if !subs.is_empty() => {
+                        self.bump();
+                        exp = self.read_power()?;
+                        break;
+                    },
+                    '.' | '*' | '/' | '^' | '(' | ')' | '}' | ']' => break,
+                    c if c.is_ascii_whitespace() => break,
+                    '[' => self.read_bracket(&mut subs)?,
+                    '{' => break,
+                    '+' | '-' | '0'..='9' => {
+                        // Exponentiation doesn't accept terms
+                        exp = self.read_exponent()?.into();
+                        break;
                     },
-                    None => break
+                    _ => {
+                        subs.push(c);
+                        self.bump();
+                    }
                 };
-                maybe_c = self.chars.next();
             }
-            Some(Token::Unit(unit::Unit::new(subs, exp, annotation)))
+            let atom_span = start..start + subs.len();
+
+            if let Some('{') = self.peek() {
+                annotation = Some(self.read_annotation()?);
+            }
+
+            let unit = unit::Unit::new(subs, exp, annotation, self.registry);
+            unit.validate(atom_span, self.registry)?;
+            Ok(Token::Unit(unit))
         }
     }
 
     impl<'a> Iterator for Tokenizer<'a> {
-        type Item = Token;
+        type Item = Result<(Token, Span)>;
 
         fn next(&mut self) -> Option<Self::Item> {
-            let mut maybe_c: Option<char> = self.visited.pop_front().or_else(|| self.chars.next());
-
-            match maybe_c {
-                Some(c) => match c {
-                    '.' | '*' => Some(Token::Mul),
-                    '/' => Some(Token::Div),
-                    '+' | '-' | '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => self.read_int(c),
-                    '}' | ')' | ']' => panic!("[Syntax Error]: '{:?}' encountered without matching opening brace.", c),
-                    ' ' => None,
-                    _ => self.read_unit(c),
-                },
-                None => None
-            }
+            let start = self.pos;
+            let single = |t: &mut Self, token: Token| {
+                t.bump();
+                Ok(token)
+            };
+
+            let token = match self.peek()? {
+                '.' | '*' => single(self, Token::Mul),
+                '/' => single(self, Token::Div),
+                '(' => single(self, Token::LParen),
+                ')' => single(self, Token::RParen),
+                '+' | '-' | '0'..='9' => self.read_int(),
+                c @ ('}' | ']') => Err(CalculumError::syntax(
+                    format!("'{}' encountered without matching opening brace.", c),
+                    start..start + 1
+                )),
+                '^' => Err(CalculumError::syntax(
+                    "'^' is only allowed after a unit (`Hz^(1/2)`) or in powers of ten (`10^3`).", start..start + 1
+                )),
+                c if c.is_ascii_whitespace() => return None,
+                _ => self.read_unit(),
+            };
+
+            Some(token.map(|t| (t, start..self.pos)))
         }
     }
 }
 
 mod parser {
+    use std::iter::Peekable;
+    use std::vec::IntoIter;
+
     use super::tokenizer::*;
+    use crate::ucum::error::{CalculumError, Result, Span};
     use crate::ucum::unit;
-    use std::ops::MulAssign;
 
+    /// A (sub-)term: a scalar factor and the units that multiply it.
+    pub type Parsed = (f64, Vec<unit::Unit>);
+
+    fn mul(lhs: &mut Parsed, rhs: Parsed) {
+        lhs.0 *= rhs.0;
+        lhs.1.extend(rhs.1);
+    }
+
+    fn div(lhs: &mut Parsed, rhs: Parsed, span: Span) -> Result<()> {
+        if rhs.0 == 0. {
+            return Err(CalculumError::DivisionByZero { span })
+        }
+        lhs.0 /= rhs.0;
+        lhs.1.extend(rhs.1.iter().map(|u| u.invert()));
+        Ok(())
+    }
+
+    /// Recursive descent over the UCUM term grammar:
+    ///
+    /// ```text
+    /// <main-term> ::= "/"<term> | <term>
+    /// <term>      ::= <term>"."<component> | <term>"/"<component> | <component>
+    /// <component> ::= <annotatable> | <factor> | <factor><annotatable>
+    ///                 | "10^"<exponent> | "("<term>")"
+    /// ```
     #[derive(Debug)]
-    pub struct Parser<'a> {
-        pub tokenizer: Tokenizer<'a>,
-        pub mag: f64,
+    pub struct Parser {
+        tokens: Peekable<IntoIter<(Token, Span)>>,
+        // End of the last token we consumed
+        end: usize,
     }
 
-    impl<'a> Parser<'a> {
-        pub fn new(tokenizer: Tokenizer<'a>) -> Self {
-            Parser {
-                tokenizer,
-                mag: 1.0
-            }
+    impl Parser {
+        pub fn new(tokenizer: Tokenizer) -> Result<Self> {
+            Ok(Parser {
+                tokens: tokenizer.collect::<Result<Vec<_>>>()?.into_iter().peekable(),
+                end: 0,
+            })
         }
 
-        pub fn mul_i(&mut self, rhs: i8) -> Option<unit::Unit> {
-            self.mag *= rhs as f64;
-            self.next()
+        fn next(&mut self) -> Option<(Token, Span)> {
+            let next = self.tokens.next();
+            if let Some((_, span)) = &next {
+                self.end = span.end;
+            }
+            next
         }
 
-        pub fn div_i(&mut self, rhs: i8) -> Option<unit::Unit> {
-            self.mag /= rhs as f64;
-            self.next()
+        fn peek(&mut self) -> Option<&Token> {
+            self.tokens.peek().map(|(t, _)| t)
         }
 
-        pub fn div_next(&mut self) -> Option<unit::Unit> {
-            let maybe_t = self.tokenizer.next();
-            match maybe_t {
-                Some(t) => match  t {
-                    Token::Unit(u) => Some(u.invert()),
-                    Token::Int(i) => self.div_i(i),
-                    _ => panic!("[Syntax Error] Invalid token {:?} encountered after division '/'", t)
-                },
-                _ => panic!("[Syntax Error] Invalid token {:?} encountered after division '/'", maybe_t)
+        pub fn parse(&mut self) -> Result<Parsed> {
+            let parsed = self.term()?;
+
+            match self.next() {
+                None => Ok(parsed),
+                Some((t, span)) => Err(CalculumError::syntax(
+                    format!("Unexpected token {:?} in unit term.", t), span
+                ))
             }
         }
-    }
 
-    impl<'a> Iterator for Parser<'a> {
-        type Item = unit::Unit;
+        fn term(&mut self) -> Result<Parsed> {
+            // A leading `/` (or nothing at all) divides (or multiplies) unity.
+            let mut parsed = match self.peek() {
+                None | Some(Token::Div) | Some(Token::RParen) => (1., vec![]),
+                _ => self.component()?,
+            };
 
-        fn next(&mut self) -> Option<Self::Item> {
-            let maybe_t = self.tokenizer.next();
-
-            match maybe_t {
-                Some(t) => match t {
-                    Token::Mul => self.next(),
-                    Token::Div => self.div_next(),
-                    Token::Unit(u) => Some(u),
-                    Token::Int(i) => self.mul_i(i)
+            loop {
+                match self.peek() {
+                    Some(Token::Mul) => {
+                        self.next();
+                        mul(&mut parsed, self.component()?);
+                    },
+                    Some(Token::Div) => {
+                        self.next();
+                        let start = self.tokens.peek().map_or(self.end, |(_, span)| span.start);
+                        let rhs = self.component()?;
+                        div(&mut parsed, rhs, start..self.end)?;
+                    },
+                    // A unit following an exponent (`s2m`) is multiplied
+                    Some(Token::Unit(_)) => mul(&mut parsed, self.component()?),
+                    _ => break Ok(parsed)
+                }
+            }
+        }
+
+        fn component(&mut self) -> Result<Parsed> {
+            match self.next() {
+                Some((Token::Unit(u), _)) => Ok((1., vec![u])),
+                Some((Token::TenPow(i), _)) => Ok((10f64.powi(i as i32), vec![])),
+                Some((Token::Int(i), _)) => {
+                    // A factor may be juxtaposed with what follows (`5s2`)
+                    let mut parsed = (i as f64, vec![]);
+                    if let Some(Token::Unit(_) | Token::LParen) = self.peek() {
+                        mul(&mut parsed, self.component()?);
+                    }
+                    Ok(parsed)
+                },
+                Some((Token::LParen, span)) => {
+                    let parsed = self.term()?;
+                    match self.next() {
+                        Some((Token::RParen, _)) => Ok(parsed),
+                        _ => Err(CalculumError::syntax(
+                            "'(' encountered without matching closing parenthesis.", span
+                        ))
+                    }
                 },
-                None => None
+                Some((t, span)) => Err(CalculumError::syntax(
+                    format!("Expected a unit, factor, or '(' but found {:?}.", t), span
+                )),
+                None => Err(CalculumError::syntax(
+                    "Expected a unit, factor, or '(' but the term ended.", self.end..self.end
+                )),
             }
         }
     }
@@ -214,69 +409,245 @@ mod parser {
 pub struct UnitTerm {
     mag: f64,
     pub units: Vec<unit::Unit>,
+    // The term as it was written (`[mi_i]/h2`), until it's combined with
+    // another term.
+    written: Option<String>,
+    // Reduced when the term is read, since only the registry it was read
+    // with knows what its atoms mean.
+    base: ReducedForm,
+    special: Option<SpecialUnit>,
+}
+
+/// A unit term reduced to base units: `[mi_i]/h` is `0.44704 m.s-1`.
+#[derive(Debug, Clone)]
+pub struct ReducedForm {
+    pub mag: f64,
+    pub units: Vec<unit::Unit>,
+}
+
+impl ReducedForm {
+    fn pow(mut self, exp: Rational) -> Result<Self> {
+        self.mag = exp.power_of(self.mag);
+        for u in self.units.iter_mut() {
+            u.exp = u.exp.checked_mul(exp).ok_or_else(|| overflow(exp))?;
+        }
+        Ok(self)
+    }
+}
+
+impl ReducedForm {
+    /// The dimension of the base units, or `None` if any of them is a
+    /// special or arbitrary unit (which don't have one).
+    pub fn dimension(&self) -> Option<DimensionVector> {
+        self.units.iter().try_fold(DimensionVector::DIMENSIONLESS, |dim, u| {
+            let base = MetricBaseUnitAtom::from_symbol(&u.atom)?;
+            Some(dim * base.dimension().pow(u.exp))
+        })
+    }
+}
+
+impl PartialEq for ReducedForm {
+    /// Magnitudes are compared up to floating point error, since the same
+    /// unit can be reached through different chains of conversions.
+    fn eq(&self, other: &ReducedForm) -> bool {
+        let tolerance = 1e-12 * self.mag.abs().max(other.mag.abs());
+        self.units == other.units && (self.mag - other.mag).abs() <= tolerance
+    }
 }
 
 /// TODO: Use iterators (rather than creating a vector with each recursive call)
-trait ReducibleUnit {
-    fn normalize_units(units: Vec<unit::Unit>) -> Vec<unit::Unit> {
-        units
+pub trait ReducibleUnit {
+    fn normalize_units(units: Vec<unit::Unit>) -> Result<Vec<unit::Unit>> {
+        Ok(units)
     }
-    fn as_base_units(&self) -> Vec<unit::Unit>;
+    fn as_base_units(&self) -> ReducedForm;
 }
 
-impl<'a, 'b> ReducibleUnit for unit::Unit {
-    /// Normalize units collapses duplicate instances of the same unit
-    /// into one, and normalizes the exponents.
-    /// It assumes your list of units is already normalized.
-    fn as_base_units(&self) -> Vec<unit::Unit> {
-        let res = unit::constants::CONVERSIONS.iter()
-            .find(|&&entry| entry.0 == self.unit());
-
-        match res {
-            Some((unit, _, reduced)) => {
-                if unit == reduced {
-                  return vec![self.clone()];
-                }
-                match &reduced[..1] {
-                    "@" | "=" | "1" => vec![self.clone()],
-                    _ => UnitTerm::new(reduced).unwrap().as_base_units(),
-                }
-            },
-            None => vec![self.clone()]
-        }
+impl ReducedForm {
+    fn product(self, other: ReducedForm) -> Result<Self> {
+        let mut units = self.units;
+        units.extend(other.units);
+        Ok(ReducedForm { mag: self.mag * other.mag, units: UnitTerm::normalize_units(units)? })
     }
 }
 
-impl UnitTerm {
-    pub fn new(s: &str) -> Option<Self> {
-        let mut p = parser::Parser::new(
-            tokenizer::Tokenizer::new(s)
-        );
+/// An exponent that doesn't fit, with an empty span (as in
+/// `Quantity::convert_to`).
+fn overflow(exponent: impl fmt::Display) -> CalculumError {
+    CalculumError::ExponentOverflow { exponent: exponent.to_string(), span: 0..0 }
+}
 
-        let mut units: Vec<unit::Unit> = vec![];
+/// Unit exponents stay in the range of the ones we read (`-128..=127`,
+/// over at most `127`), whether they're read, multiplied or raised to a
+/// power.
+fn check_exponents(units: &[unit::Unit]) -> Result<()> {
+    let in_range = |e: Rational| i8::try_from(e.numer()).is_ok() && i8::try_from(e.denom()).is_ok();
+    match units.iter().find(|u| !in_range(u.exp)) {
+        Some(u) => Err(overflow(u.exp)),
+        None => Ok(()),
+    }
+}
+
+/// Follows the registry's definitions down to the base units, multiplying
+/// in the prefix and each factor along the way, then raises the result to
+/// the unit's exponent.
+fn reduce_unit(u: &unit::Unit, registry: &UnitRegistry) -> Result<ReducedForm> {
+    let atom = unit::Unit {
+        prefix: "".to_string(),
+        atom: u.atom.clone(),
+        exp: Rational::ONE,
+        annotation: None
+    };
+
+    let mut reduced = match registry.unit(&u.atom) {
+        // Annotations on their own (`{rbc}`) are unity
+        _ if u.atom.is_empty() => ReducedForm { mag: 1., units: vec![] },
+        // Square roots are ratio units, with a fractional exponent
+        Some(def) if special::root_of(def).is_some() => {
+            let root = special::root_of(def).expect("Checked above");
+            let mut reduced = UnitTerm::new(&root.unit, registry)
+                .expect("Definitions are valid unit terms")
+                .base;
+            reduced.mag *= root.value;
+            reduced.pow(Rational::new(1, 2))?
+        },
+        // Special (see `ucum::special`) & arbitrary units are kept as they are
+        Some(def) if def.is_special || def.is_arbitrary && def.value.unit == "1" => {
+            ReducedForm { mag: 1., units: vec![atom] }
+        },
+        Some(def) if !unit::is_base_atom(&u.atom, registry) => {
+            let mut reduced = UnitTerm::new(&def.value.unit, registry)
+                .expect("Definitions are valid unit terms")
+                .base;
+            reduced.mag *= def.value.factor;
+            reduced
+        },
+        // Base units
+        _ => ReducedForm { mag: 1., units: vec![atom] },
+    };
+
+    reduced.mag *= u.prefix_factor(registry);
+    reduced.pow(u.exp)
+}
 
-        loop {
-            let maybe_u = p.next();
-            match maybe_u {
-                Some(u) => units.push(u.clone()),
-                None => break
+impl UnitTerm {
+    pub fn new(s: &str, registry: &UnitRegistry) -> Result<Self> {
+        let (mag, units) = parser::Parser::new(
+            tokenizer::Tokenizer::new(s, registry)
+        )?.parse()?;
+
+        // Special units only make sense on their own (`Cel`, not `Cel.m`)
+        if let Some(u) = units.iter().find(|u| special::is_special(&u.atom)) {
+            if units.len() > 1 || u.exp != Rational::ONE || mag != 1. {
+                return Err(CalculumError::SpecialUnit { unit: u.unit(), span: 0..s.len() })
             }
         }
 
-        Some(UnitTerm {
-            mag: p.mag,
-            units: Self::normalize_units(units),
+        let units = Self::normalize_units(units).map_err(|e| e.at(0..s.len()))?;
+        check_exponents(&units).map_err(|e| e.at(0..s.len()))?;
+        let base = units.iter()
+            .try_fold(ReducedForm { mag, units: vec![] }, |base, u| base.product(reduce_unit(u, registry)?))
+            .map_err(|e| e.at(0..s.len()))?;
+        let special = match &units[..] {
+            [u] => SpecialUnit::find(u, registry),
+            _ => None,
+        };
+
+        Ok(UnitTerm {
+            mag,
+            units,
+            written: Some(s.to_string()),
+            base,
+            special,
+        })
+    }
+
+    /// Raises every unit to the `exp`th power (`m/s` squared is `m2/s2`,
+    /// and the square root of `m2` is `m`). Unit exponents stay in range
+    /// (see `check_exponents`).
+    ///
+    /// The error's span is empty, as in `Quantity::convert_to`.
+    pub fn pow(&self, exp: Rational) -> Result<Self> {
+        if exp == Rational::ONE {
+            return Ok(self.clone())
+        }
+        if self.special.is_some() {
+            return Err(CalculumError::SpecialUnit { unit: self.to_string(), span: 0..0 })
+        }
+
+        let units = self.units.iter()
+            .map(|u| u.exp.checked_mul(exp).map(|e| unit::Unit { exp: e, ..u.clone() }))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| overflow(exp))?;
+        check_exponents(&units).map_err(|_| overflow(exp))?;
+        let mut base = self.base.clone().pow(exp)?;
+        base.units = Self::normalize_units(base.units)?;
+
+        Ok(UnitTerm {
+            mag: exp.power_of(self.mag),
+            units: Self::normalize_units(units)?,
+            written: None,
+            base,
+            special: None,
         })
     }
 
+    /// Splits the factor off a term that's no longer written out (`m/5s`
+    /// times `s` is `0.2` times `m`), since only the written form shows it.
+    pub fn take_factor(&mut self) -> f64 {
+        if self.written.is_some() || self.mag == 1. {
+            return 1.
+        }
+        let factor = self.mag;
+        self.mag = 1.;
+        self.base.mag /= factor;
+        factor
+    }
+
     pub fn invert(&mut self) {
+        self.mag = 1. / self.mag;
         self.units = self.units.iter().map(|u| u.invert()).collect();
+        self.written = None;
+        self.base.mag = 1. / self.base.mag;
+        self.base.units = self.base.units.iter().map(|u| u.invert()).collect();
+        self.special = None;
+    }
+
+    /// The product of the terms. Scaling by a plain number keeps the term
+    /// as it is (so `2 * 37 Cel` is still in `Cel`). Unit exponents stay in
+    /// range (see `check_exponents`).
+    pub fn checked_mul(mut self, other: &UnitTerm) -> Result<UnitTerm> {
+        if other.is_unity() {
+            return Ok(self)
+        }
+        if self.is_unity() {
+            return Ok(other.clone())
+        }
+        self.mag *= other.mag;
+        self.units.extend(other.units.iter().cloned());
+        self.units = Self::normalize_units(self.units)?;
+        check_exponents(&self.units)?;
+        self.written = None;
+        self.base = self.base.product(other.base.clone())?;
+        self.special = None;
+        Ok(self)
+    }
+
+    pub fn checked_div(self, other: &UnitTerm) -> Result<UnitTerm> {
+        if other.is_unity() {
+            return Ok(self)
+        }
+        let mut inverse = other.clone();
+        inverse.invert();
+        self.checked_mul(&inverse)
     }
 }
 
 impl ReducibleUnit for UnitTerm {
-    fn normalize_units(mut units: Vec<unit::Unit>) -> Vec<unit::Unit> {
-        units.sort_by(|a, b| a.atom.cmp(&b.atom));
+    /// Collapses duplicate instances of the same unit into one, summing
+    /// their exponents, and drops the units that cancel out.
+    fn normalize_units(mut units: Vec<unit::Unit>) -> Result<Vec<unit::Unit>> {
+        units.sort_by(|a, b| (&a.atom, &a.prefix).cmp(&(&b.atom, &b.prefix)));
         let mut result: Vec<unit::Unit> = vec![];
 
         for unit in units.into_iter() {
@@ -284,97 +655,130 @@ impl ReducibleUnit for UnitTerm {
                 None => result.push(unit),
                 Some(last) => {
 
-                    if last.exp == 0 {
+                    if last.exp.is_zero() {
                         *last = unit
                     } else if last.has_same_unit(&unit) {
-                        last.exp += unit.exp;
+                        last.exp = last.exp.checked_add(unit.exp)
+                            .ok_or_else(|| overflow(format!("{} + {}", last.exp, unit.exp)))?;
                     } else {
                         result.push(unit)
                     }
                 }
             }
         }
-        result
+        result.retain(|u| !u.exp.is_zero());
+        Ok(result)
     }
 
-    fn as_base_units(&self) -> Vec<unit::Unit> {
-        let mut units: Vec<unit::Unit> = self.units.iter()
-            .flat_map(|u| u.as_base_units())
-            .collect();
-
-        Self::normalize_units(units)
+    fn as_base_units(&self) -> ReducedForm {
+        self.base.clone()
     }
 }
 
-impl PartialEq for UnitTerm {
-    fn eq(&self, other: &UnitTerm) -> bool {
-        self.as_base_units() == other.as_base_units()
+impl UnitTerm {
+    /// The special unit, if that's what the term is (e.g., `Cel`).
+    pub fn special(&self) -> Option<SpecialUnit> {
+        self.special.clone()
     }
-}
 
-impl fmt::Debug for UnitTerm {
-    fn fmt(&self, f: &mut fmt::
-    Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{} ({})",
-            self, unit::Units(&self.as_base_units()))
+    /// Whether the term involves an arbitrary unit (e.g., `m[iU]/mL`).
+    pub fn is_arbitrary(&self) -> bool {
+        self.base.units.iter().any(|u| u.is_arbitrary())
     }
-}
 
-impl fmt::Display for UnitTerm {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", unit::Units(&self.units))
+    /// Whether any unit has a fractional exponent (e.g., `Hz^(1/2)`).
+    pub fn has_fractional_exponents(&self) -> bool {
+        self.units.iter().any(|u| !u.exp.is_integer())
     }
-}
 
-impl ops::AddAssign for UnitTerm {
-    fn add_assign(&mut self, other: Self) {
-        if self != &other {
-            panic!("Units {:?} and {:?} are not compatible", self, other)
-        }
+    /// Unitless (`1`), as for plain numbers.
+    pub fn is_unity(&self) -> bool {
+        self.units.is_empty() && self.mag == 1.
+    }
+
+    /// Special units take on the dimension of their ratio unit (`Cel` is a
+    /// temperature like `K`).
+    pub fn dimensional_base_units(&self) -> ReducedForm {
+        self.special()
+            .and_then(|special| special.ratio_term().ok())
+            .map_or_else(|| self.as_base_units(), |ratio| ratio.as_base_units())
     }
-}
 
-impl ops::SubAssign for UnitTerm {
-    fn sub_assign(&mut self, other: Self) {
-        if self != &other {
-            panic!("Units {:?} and {:?} are not compatible", self, other)
+    pub fn dimension(&self) -> Option<DimensionVector> {
+        self.dimensional_base_units().dimension()
+    }
+
+    /// The dimension as it's shown in errors (`L.T-1` for `km/h`), or the
+    /// base units for terms without one (`[iU]`, `[currency]`).
+    pub fn dimension_name(&self) -> String {
+        self.dimension().map_or_else(|| self.dimensional_base_units().to_string(), |dim| dim.to_string())
+    }
+
+    /// Whether the two terms share a dimension, e.g., `km/h` & `m/s`.
+    /// Terms without a dimension have to reduce to the same units instead.
+    pub fn is_commensurable(&self, other: &UnitTerm) -> bool {
+        let (base, other_base) = (self.dimensional_base_units(), other.dimensional_base_units());
+
+        match (base.dimension(), other_base.dimension()) {
+            (Some(dim), Some(other_dim)) => dim == other_dim,
+            _ => base.units == other_base.units,
         }
     }
 }
 
-impl ops::MulAssign for UnitTerm {
-    fn mul_assign(&mut self, other: Self) {
-        self.units.extend(other.units);
-        self.units = Self::normalize_units((*self.units).to_vec());
+impl PartialEq for UnitTerm {
+    fn eq(&self, other: &UnitTerm) -> bool {
+        self.as_base_units() == other.as_base_units()
     }
 }
 
-impl ops::DivAssign for UnitTerm {
-    fn div_assign(&mut self, other: Self) {
-        self.invert();
-        self.units.extend(other.units);
-        self.invert();
+impl fmt::Debug for UnitTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let base = self.as_base_units();
+        if base.mag == 1. {
+            write!(f, "{} ({})", self, unit::Units(&base.units))
+        } else {
+            write!(f, "{} ({} {})", self, base.mag, unit::Units(&base.units))
+        }
+    }
+}
 
-        self.units = Self::normalize_units((*self.units).to_vec());
+impl fmt::Display for ReducedForm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.units.len() {
+            0 => write!(f, "1"),
+            _ => write!(f, "{}", unit::Units(&self.units)),
+        }
     }
 }
 
+impl fmt::Display for UnitTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.written {
+            Some(s) => write!(f, "{}", s),
+            None => write!(f, "{}", unit::Units(&self.units)),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::tokenizer::{Token, Tokenizer};
     use super::*;
+    use crate::ucum::essence::essence;
 
     // -- Tokenizer
+    fn tokenize(s: &str) -> Vec<Token> {
+        Tokenizer::new(s, &UnitRegistry::default()).map(|t| t.unwrap().0).collect()
+    }
+
     fn unit_token(u: &str, p: i8) -> Token {
-        Token::Unit(unit::Unit::new(u.to_string(), p, None))
+        Token::Unit(unit::Unit::new(u.to_string(), p.into(), None, &UnitRegistry::default()))
     }
     
     #[test]
     fn it_splits_on_mul_and_div() {
-        let tokens: Vec<Token> = Tokenizer::new("m.kg/s2").collect();
+        let tokens: Vec<Token> = tokenize("m.kg/s2");
 
         assert_eq!(tokens, vec![
             unit_token("m", 1),
@@ -386,7 +790,7 @@ mod tests {
 
      #[test]
      fn it_splits_on_pos_and_neg() {
-        let tokens: Vec<Token> = Tokenizer::new("m.kg+5.s-2").collect();
+        let tokens: Vec<Token> = tokenize("m.kg+5.s-2");
         assert_eq!(tokens, vec![
             unit_token("m", 1),
             Token::Mul,
@@ -398,24 +802,24 @@ mod tests {
 
     #[test]
     fn it_reads_annotations() {
-        let tokens: Vec<Token> = Tokenizer::new("m{meters}").collect();
+        let tokens: Vec<Token> = tokenize("m{meters}");
 
         assert_eq!(tokens, vec![
-            Token::Unit(unit::Unit::new("m".to_string(), 1, Some("meters".to_string())))
+            Token::Unit(unit::Unit::new("m".to_string(), Rational::ONE, Some("meters".to_string()), &UnitRegistry::default()))
         ]);
 
-        let tokens: Vec<Token> = Tokenizer::new("m.{meters}").collect();
+        let tokens: Vec<Token> = tokenize("m.{meters}");
 
         assert_eq!(tokens, vec![
-            Token::Unit(unit::Unit::new("m".to_string(), 1, None)),
+            Token::Unit(unit::Unit::new("m".to_string(), Rational::ONE, None, &UnitRegistry::default())),
             Token::Mul,
-            Token::Unit(unit::Unit::new("".to_string(), 1, Some("meters".to_string())))
+            Token::Unit(unit::Unit::new("".to_string(), Rational::ONE, Some("meters".to_string()), &UnitRegistry::default()))
         ])
     }
 
     #[test]
     fn it_reads_ints() {
-        let tokens: Vec<Token> = Tokenizer::new("10.m3.02kg/5s").collect();
+        let tokens: Vec<Token> = tokenize("10.m3.02kg/5s");
 
         assert_eq!(tokens, vec![
             Token::Int(10),
@@ -430,15 +834,78 @@ mod tests {
         ])
     }
 
+    #[test]
+    fn it_reads_parentheses() {
+        let tokens: Vec<Token> = tokenize("kg/(m.s2)");
+
+        assert_eq!(tokens, vec![
+            unit_token("kg", 1),
+            Token::Div,
+            Token::LParen,
+            unit_token("m", 1),
+            Token::Mul,
+            unit_token("s", 2),
+            Token::RParen,
+        ])
+    }
+
+    #[test]
+    fn it_reads_powers_of_ten() {
+        let tokens: Vec<Token> = tokenize("10^3.kg");
+        assert_eq!(tokens, vec![Token::TenPow(3), Token::Mul, unit_token("kg", 1)]);
+
+        let tokens: Vec<Token> = tokenize("10*-13.s");
+        assert_eq!(tokens, vec![Token::TenPow(-13), Token::Mul, unit_token("s", 1)]);
+
+        let tokens: Vec<Token> = tokenize("10*m");
+        assert_eq!(tokens, vec![Token::Int(10), Token::Mul, unit_token("m", 1)]);
+    }
+
+    #[test]
+    fn it_reads_brackets_as_part_of_the_atom() {
+        let tokens: Vec<Token> = tokenize("m[H2O].[in_i]2/B[10.nV]");
+
+        assert_eq!(tokens, vec![
+            unit_token("m[H2O]", 1),
+            Token::Mul,
+            unit_token("[in_i]", 2),
+            Token::Div,
+            unit_token("B[10.nV]", 1),
+        ])
+    }
+
+    #[test]
+    fn it_reads_rational_powers() {
+        let half_hz = Token::Unit(unit::Unit::new("Hz".to_string(), Rational::new(1, 2), None, &UnitRegistry::default()));
+
+        assert_eq!(tokenize("V/Hz^(1/2)"), vec![unit_token("V", 1), Token::Div, half_hz]);
+        assert_eq!(tokenize("m^2.[ft_i]^-1"), vec![unit_token("m", 2), Token::Mul, unit_token("[ft_i]", -1)]);
+    }
+
+    #[test]
+    fn it_reports_invalid_powers() {
+        use crate::ucum::error::CalculumError;
+
+        let term = |s| UnitTerm::new(s, &UnitRegistry::default());
+
+        assert_eq!(term("Hz^(1/0)").unwrap_err(), CalculumError::DivisionByZero { span: 3..8 });
+        assert_eq!(
+            term("Hz^(1/2").unwrap_err(),
+            CalculumError::syntax("'(' encountered without matching closing parenthesis.", 3..4)
+        );
+        assert_eq!(term("m^").unwrap_err(), CalculumError::syntax("Expected an exponent.", 2..2));
+        assert!(matches!(term("m2^2"), Err(CalculumError::Syntax { .. })));
+    }
+
     // -- UnitTerm
 
     fn unit(u: &str, p: i8) -> unit::Unit {
-        unit::Unit::new(u.to_string(), p, None)
+        unit::Unit::new(u.to_string(), p.into(), None, &UnitRegistry::default())
     }
 
     #[test]
     fn it_interprets_division_of_units() {
-        let unit_term = UnitTerm::new("m.kg/s2").unwrap();
+        let unit_term = UnitTerm::new("m.kg/s2", &UnitRegistry::default()).unwrap();
 
         assert_eq!(unit_term.mag, 1.);
         assert_eq!(unit_term.units, vec![
@@ -450,15 +917,102 @@ mod tests {
 
     #[test]
     fn it_interprets_division_of_scalars() {
-        let unit_term = UnitTerm::new("m.kg/5s2").unwrap();
+        let unit_term = UnitTerm::new("m.kg/5s2", &UnitRegistry::default()).unwrap();
 
         assert_eq!(unit_term.mag, 0.2);
     }
 
+    #[test]
+    fn it_interprets_parenthesized_terms() {
+        let unit_term = UnitTerm::new("kg/(m.s2)", &UnitRegistry::default()).unwrap();
+
+        assert_eq!(unit_term.units, vec![
+            unit("kg", 1),
+            unit("m", -1),
+            unit("s", -2)
+        ]);
+
+        let unit_term = UnitTerm::new("kg/(m/(s.4))", &UnitRegistry::default()).unwrap();
+
+        assert_eq!(unit_term.mag, 4.);
+        assert_eq!(unit_term.units, vec![
+            unit("kg", 1),
+            unit("m", -1),
+            unit("s", 1)
+        ]);
+    }
+
+    #[test]
+    fn it_interprets_powers_of_ten() {
+        let unit_term = UnitTerm::new("10^3.kg", &UnitRegistry::default()).unwrap();
+
+        assert_eq!(unit_term.mag, 1e3);
+        assert_eq!(unit_term.units, vec![unit("kg", 1)]);
+
+        assert_eq!(UnitTerm::new("m/10*-2", &UnitRegistry::default()).unwrap().mag, 1e2);
+    }
+
+    #[test]
+    fn it_interprets_unary_division() {
+        let unit_term = UnitTerm::new("/s", &UnitRegistry::default()).unwrap();
+        assert_eq!(unit_term.units, vec![unit("s", -1)]);
+
+        let unit_term = UnitTerm::new("/[pi].A/m", &UnitRegistry::default()).unwrap();
+        assert_eq!(unit_term.units, vec![
+            unit("A", 1),
+            unit("[pi]", -1),
+            unit("m", -1)
+        ]);
+
+        let unit_term = UnitTerm::new("[pi]/4.[mil_i]2", &UnitRegistry::default()).unwrap();
+        assert_eq!(unit_term.mag, 0.25);
+        assert_eq!(unit_term.units, vec![
+            unit("[mil_i]", 2),
+            unit("[pi]", 1)
+        ]);
+    }
+
+    #[test]
+    fn it_parses_every_definition() {
+        for def in essence().units.iter() {
+            // Special units are defined by functions, not terms
+            if !def.is_special {
+                let reduced = &def.value.unit;
+                assert!(UnitTerm::new(reduced, &UnitRegistry::default()).is_ok(), "{} = {}: {:?}", def.code, reduced, UnitTerm::new(reduced, &UnitRegistry::default()).err());
+            }
+        }
+    }
+
+    #[test]
+    fn it_reports_errors_with_spans() {
+        use crate::ucum::error::CalculumError;
+
+        assert_eq!(
+            UnitTerm::new("kg/(m.s2", &UnitRegistry::default()).unwrap_err(),
+            CalculumError::syntax("'(' encountered without matching closing parenthesis.", 3..4)
+        );
+        assert_eq!(
+            UnitTerm::new("m.s200", &UnitRegistry::default()).unwrap_err(),
+            CalculumError::ExponentOverflow { exponent: "200".to_string(), span: 3..6 }
+        );
+        assert_eq!(
+            UnitTerm::new("m/0", &UnitRegistry::default()).unwrap_err(),
+            CalculumError::DivisionByZero { span: 2..3 }
+        );
+        assert!(matches!(
+            UnitTerm::new("m.s)", &UnitRegistry::default()).unwrap_err(),
+            CalculumError::Syntax { span, .. } if span == (3..4)
+        ));
+        assert!(matches!(
+            UnitTerm::new("kg.m[in_i]", &UnitRegistry::default()).unwrap_err(),
+            CalculumError::PrefixNotAllowed { span, .. } if span == (3..10)
+        ));
+    }
+
     // -- Conversions
     #[test]
     fn it_combines_redundant_units() {
-        let base_units = |s| UnitTerm::new(s).unwrap();
+        let base_units = |s| UnitTerm::new(s, &UnitRegistry::default()).unwrap();
 
         assert_eq!(base_units("m.m.m"), base_units("m3"));
         assert_eq!(base_units("m.m.m/m.m/m"), base_units("m2"));
@@ -467,7 +1021,7 @@ mod tests {
 
     #[test]
     fn it_converts_composite_si_units() {
-        let base_units = |s| UnitTerm::new(s).unwrap();
+        let base_units = |s| UnitTerm::new(s, &UnitRegistry::default()).unwrap();
 
         println!("{}", base_units("m.kg/s2"));
 
@@ -478,4 +1032,135 @@ mod tests {
         assert_eq!(base_units("g.cm/s2"), base_units("dyn"));
     }
 
-}
\ No newline at end of file
+    fn reduced(s: &str) -> ReducedForm {
+        UnitTerm::new(s, &UnitRegistry::default()).unwrap().as_base_units()
+    }
+
+    fn assert_reduces_to(s: &str, mag: f64, units: &str) {
+        let r = reduced(s);
+        let tolerance = 1e-12 * mag.abs();
+
+        assert!((r.mag - mag).abs() <= tolerance, "{}: {} != {}", s, r.mag, mag);
+        assert_eq!(r.units, UnitTerm::new(units, &UnitRegistry::default()).unwrap().units, "{}", s);
+    }
+
+    #[test]
+    fn it_applies_prefixes() {
+        assert_reduces_to("km", 1e3, "m");
+        assert_reduces_to("mm2", 1e-6, "m2");
+        assert_reduces_to("/ms", 1e3, "s-1");
+        assert_reduces_to("kg", 1e3, "g");
+
+        assert_ne!(UnitTerm::new("km", &UnitRegistry::default()).unwrap(), UnitTerm::new("m", &UnitRegistry::default()).unwrap());
+        assert!(UnitTerm::new("km", &UnitRegistry::default()).unwrap().is_commensurable(&UnitTerm::new("m", &UnitRegistry::default()).unwrap()));
+    }
+
+    #[test]
+    fn it_applies_conversion_factors() {
+        assert_reduces_to("[mi_i]", 1609.344, "m");
+        assert_reduces_to("[mi_i]/h", 1609.344 / 3600., "m/s");
+        assert_reduces_to("[sft_i]", 0.09290304, "m2");
+        assert_reduces_to("deg", std::f64::consts::PI / 180., "rad");
+        assert_reduces_to("10^3.L", 1., "m3");
+        assert_reduces_to("[cml_i]", std::f64::consts::PI / 4. * 0.0000254 * 0.0000254, "m2");
+        assert_reduces_to("m.kg/5s2", 200., "g.m.s-2");
+    }
+
+    #[test]
+    fn it_matches_the_essence_values() {
+        assert_reduces_to("%", 0.01, "");
+        assert_reduces_to("[ppm]", 1e-6, "");
+        assert_reduces_to("[e]", 1.60217733e-19, "C");
+        assert_reduces_to("[G]", 6.67259e-14, "m3.g-1.s-2");
+        assert_reduces_to("G", 1e-1, "g.s-1.C-1");
+        assert_reduces_to("g%", 1e4, "g.m-3");
+    }
+
+    /// `calculum-core` keeps its own table of common units, which has to
+    /// agree with the essence.
+    #[test]
+    fn it_matches_the_core_tables() {
+        use calculum_core::constants::{customary::CUSTOMARY_UNITS, metric::DERIVED_UNITS};
+
+        for (atom, factor, units) in DERIVED_UNITS.iter().chain(CUSTOMARY_UNITS.iter()) {
+            let units: Vec<String> = units.iter().map(|(base, power)| format!("{}{}", base, power)).collect();
+            assert_reduces_to(atom, *factor, &units.join("."));
+        }
+    }
+
+    #[test]
+    fn it_cancels_units() {
+        assert_reduces_to("m/m", 1., "");
+        assert_reduces_to("km.m.km/m3", 1e6, "");
+    }
+
+    fn dimension(s: &str) -> Option<DimensionVector> {
+        UnitTerm::new(s, &UnitRegistry::default()).unwrap().dimension()
+    }
+
+    #[test]
+    fn it_reports_dimensions() {
+        let force = DimensionVector::MASS * DimensionVector::LENGTH / DimensionVector::TIME.powi(2);
+
+        assert_eq!(dimension("N"), Some(force));
+        assert_eq!(dimension("[lbf_av]"), Some(force));
+        assert_eq!(dimension("mol/l"), Some(DimensionVector::AMOUNT / DimensionVector::LENGTH.powi(3)));
+        assert_eq!(dimension("[pi].%"), Some(DimensionVector::DIMENSIONLESS));
+        assert_eq!(dimension("deg"), Some(DimensionVector::ANGLE));
+        assert_eq!(dimension("[arb'U]"), None);
+    }
+
+    #[test]
+    fn it_compares_dimensions_when_checking_commensurability() {
+        let term = |s| UnitTerm::new(s, &UnitRegistry::default()).unwrap();
+
+        assert!(term("km/h").is_commensurable(&term("m/s")));
+        assert!(term("J").is_commensurable(&term("N.m")));
+        assert!(!term("mol/l").is_commensurable(&term("/l")));
+        assert!(!term("[arb'U]").is_commensurable(&term("1")));
+    }
+
+    #[test]
+    fn it_reduces_rational_powers() {
+        let term = |s| UnitTerm::new(s, &UnitRegistry::default()).unwrap();
+        let per_root_hz = DimensionVector::TIME.pow(Rational::new(1, 2));
+
+        assert_eq!(term("V/Hz^(1/2)").dimension(), Some(term("V").dimension().unwrap() * per_root_hz));
+        assert_eq!(term("[m/s2/Hz^(1/2)]").dimension(), term("m/s2").dimension().map(|dim| dim * per_root_hz));
+        assert_eq!(term("[m/s2/Hz^(1/2)]"), term("m/s2/Hz^(1/2)"));
+        assert!(term("[m/s2/Hz^(1/2)]").special().is_none());
+        assert_eq!(term("kHz^(1/2)").as_base_units().mag, 1e3f64.sqrt());
+    }
+
+    #[test]
+    fn it_multiplies_rational_powers() {
+        let term = |s| UnitTerm::new(s, &UnitRegistry::default()).unwrap();
+
+        let noise = term("V/Hz^(1/2)").checked_mul(&term("Hz^(1/2)")).unwrap();
+        assert_eq!(noise, term("V"));
+        assert_eq!(noise.to_string(), "V");
+
+        let noise = term("V/Hz^(1/2)").checked_div(&term("s^(1/2)")).unwrap();
+        assert_eq!(noise.to_string(), "Hz^(-1/2).V.s^(-1/2)");
+        assert_eq!(term(&noise.to_string()), noise);
+    }
+
+    #[test]
+    fn it_keeps_merged_exponents_in_range() {
+        let term = |s| UnitTerm::new(s, &UnitRegistry::default());
+        let overflow = |exponent: &str, span| Err(CalculumError::ExponentOverflow { exponent: exponent.to_string(), span });
+
+        let s = "m^(1/127).m^(1/113).m^(1/109).m^(1/107).m^(1/103)";
+        assert!(matches!(term(s), Err(CalculumError::ExponentOverflow { span, .. }) if span == (0..s.len())));
+        assert_eq!(term("m127.m"), overflow("128", 0..6));
+        assert!(matches!(
+            term("m^(1/127)/m^(1/113)"),
+            Err(CalculumError::ExponentOverflow { exponent, .. }) if exponent == "-14/14351"
+        ));
+
+        let m127 = term("m127").unwrap();
+        assert_eq!(m127.clone().checked_mul(&m127), overflow("254", 0..0));
+        assert_eq!(m127.clone().checked_div(&term("m-1").unwrap()), overflow("128", 0..0));
+        assert!(m127.checked_mul(&term("m-127").unwrap()).unwrap().units.is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::{env, fs, process};
+
+fn calculum(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_calculum-lang"));
+    command.args(args).stdin(Stdio::null());
+    command
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// A script in a file of its own (named after the test, so that tests can
+/// run in parallel).
+fn script(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("calculum-{}-{}.calc", name, process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn run_script(name: &str, contents: &str) -> Output {
+    let path = script(name, contents);
+    let output = calculum(&["run", path.to_str().unwrap()]).output().unwrap();
+    fs::remove_file(path).unwrap();
+    output
+}
+
+fn pipe(args: &[&str], input: &str) -> Output {
+    let mut child = calculum(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn it_runs_statements() {
+    let output = calculum(&["-e", "10 [mi_i] -> km", "-e", "x = 2 m", "-e", "x * 3"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "16.09344 'km'\n6 'm'\n");
+}
+
+#[test]
+fn it_stops_at_the_first_error_in_statements() {
+    let output = calculum(&["-e", "1 m", "-e", "1 m + 1 s", "-e", "2 m"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "1 'm'\n");
+    assert!(stderr(&output).starts_with("-e:2:1: [Dimension Error]"), "{}", stderr(&output));
+}
+
+#[test]
+fn it_runs_scripts() {
+    let output = run_script("runs", "define [pace] = 1 s/m\nd = 3 km\n\nd * 5 [pace] -> min\n");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "250 'min'\n");
+}
+
+#[test]
+fn it_checks_scripts_before_running_them() {
+    let output = run_script("checks", "2 m\nb = 1 m + 1 s\nc = 1 kg -> m\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    let errors: Vec<String> = stderr(&output).lines()
+        .filter(|line| line.contains("Error]"))
+        .map(|line| line.split(": ").next().unwrap().to_string())
+        .collect();
+    assert_eq!(errors.len(), 2, "{}", stderr(&output));
+    assert!(errors[0].ends_with(".calc:2:5") && errors[1].ends_with(".calc:3:5"), "{:?}", errors);
+}
+
+#[test]
+fn it_runs_scripts_with_functions_of_later_variables() {
+    let output = run_script("later", "f(x) = x * k\nk = 2\nf(3)\n");
+
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "6 ''\n");
+}
+
+#[test]
+fn it_reports_missing_scripts() {
+    let output = calculum(&["run", "/nonexistent/trip.calc"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("/nonexistent/trip.calc: "));
+}
+
+#[test]
+fn it_runs_piped_lines() {
+    let output = pipe(&[], "x = 2 m\n\tx * x\n");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "4 'm2'\n");
+}
+
+#[test]
+fn it_stops_at_the_first_error_in_piped_lines() {
+    let output = pipe(&[], "1 m\nsqrt(-1)\n2 m\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "1 'm'\n");
+    assert!(stderr(&output).starts_with("<stdin>:2:1: "), "{}", stderr(&output));
+}
+
+#[test]
+fn it_rejects_invalid_arguments() {
+    for args in [&["--bogus"][..], &["-e"], &["--format", "xml"], &["run"], &["run", "a", "b"]] {
+        let output = calculum(args).output().unwrap();
+
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert_eq!(stdout(&output), "", "{:?}", args);
+        assert!(stderr(&output).contains("Usage:"), "{:?}", args);
+    }
+}
+
+#[test]
+fn it_shows_help() {
+    let output = calculum(&["--help"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("Usage:"));
+}
+
+#[test]
+fn it_writes_values_to_stdout_and_errors_to_stderr_as_json() {
+    let output = pipe(&["--format", "json"], "1 m < 1 km\n1 m + 1 s\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "{\"source\":\"<stdin>\",\"line\":1,\"input\":\"1 m < 1 km\",\"kind\":\"boolean\",\"value\":\"true\",\"boolean\":true}\n"
+    );
+    assert!(stderr(&output).starts_with("{\"source\":\"<stdin>\",\"line\":2,\"input\":\"1 m + 1 s\",\"error\":{\"kind\":\"Incommensurable\""));
+    assert_eq!(stderr(&output).lines().count(), 1);
+}